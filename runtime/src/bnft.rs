@@ -1,20 +1,79 @@
 use parity_codec::{Decode, Encode};
 use support::{StorageValue, StorageMap, ensure, dispatch::Result, decl_module, decl_storage, decl_event};
-use support::traits::{Currency, WithdrawReason, ExistenceRequirement};
+use support::traits::{Currency, ReservableCurrency, WithdrawReason, ExistenceRequirement};
 use runtime_primitives::traits::{Zero, Hash, Saturating, As, CheckedAdd, CheckedMul, CheckedDiv};
 use {system::ensure_signed, timestamp};
 use rstd::prelude::*;
 use crate::token;
 use crate::id;
+use crate::preimage;
 use runtime_io::keccak_256;
 
-pub trait Trait: balances::Trait + timestamp::Trait + token::Trait + id::Trait {
+// number of templates that can be instantiated automatically in one block
+pub const MAX_SCHEDULED_TEMPLATES_PER_BLOCK: usize = 10;
+// number of later blocks tried when the block a template is due in is full
+pub const MAX_SCHEDULE_DELAY: u64 = 10;
+
+pub trait Trait: balances::Trait + timestamp::Trait + token::Trait + id::Trait + preimage::Trait {
     type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+    // native currency classes can pay bounties and take stakes in
+    type NativeCurrency: ReservableCurrency<Self::AccountId, Balance = <Self as token::Trait>::TokenBalance>;
+}
+
+// asset a class takes stakes and pays bounties in
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq)]
+pub enum PayoutAsset {
+    // native balance, held with reserve/unreserve
+    Native,
+    // an asset of the ERC20 token module, held in locked deposits
+    Token(token::AssetId),
+}
+
+impl Default for PayoutAsset {
+    fn default() -> Self {
+        PayoutAsset::Token(token::DEFAULT_ASSET)
+    }
+}
+
+// pays token bounties as vesting schedules instead of lump sums
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Encode, Decode, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BountyVesting {
+    // blocks after verification before anything can be released
+    cliff: u32,
+    // blocks over which the bounty matures
+    duration: u32,
 }
 
 #[cfg_attr(feature = "std", derive(Debug))]
 #[derive(Encode, Decode, Default, Clone, PartialEq, Eq)]
 pub struct BnftClass<Hash, Balance, Moment, AccountId> {
+    name: Hash,
+    total_supply: Balance,
+    beneficiary_credentials: Vec<(AccountId, u16)>,
+    // topics the beneficiary needs a claim on from any issuer trusted for the topic
+    beneficiary_topics: Vec<u16>,
+    verifier_credentials: Vec<(AccountId, u16)>,
+    transfer_bounty: Balance,
+    verification_bounty: Balance,
+    stake: Balance,
+    payout_asset: PayoutAsset,
+    bounty_vesting: Option<BountyVesting>,
+    expiry: Moment,
+    description: Hash,
+    ricardian_contract: Hash,
+    creator: AccountId,
+    created_on: Moment,
+    funded: bool,
+    funded_on: Option<Moment>,
+    funding_period: Moment,
+}
+
+// class layout before payout assets, bounty vesting and topics were added, see `migrate_bnft_class`
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Encode, Decode, Default, Clone, PartialEq, Eq)]
+pub struct LegacyBnftClass<Hash, Balance, Moment, AccountId> {
     name: Hash,
     total_supply: Balance,
     beneficiary_credentials: Vec<(AccountId, u16)>,
@@ -32,6 +91,73 @@ pub struct BnftClass<Hash, Balance, Moment, AccountId> {
     funding_period: Moment,
 }
 
+// existing classes pay in the default token asset, as lump sums, and require no topics
+impl<Hash, Balance, Moment, AccountId> From<LegacyBnftClass<Hash, Balance, Moment, AccountId>> for BnftClass<Hash, Balance, Moment, AccountId> {
+    fn from(class: LegacyBnftClass<Hash, Balance, Moment, AccountId>) -> Self {
+        BnftClass {
+            name: class.name,
+            total_supply: class.total_supply,
+            beneficiary_credentials: class.beneficiary_credentials,
+            beneficiary_topics: Vec::new(),
+            verifier_credentials: class.verifier_credentials,
+            transfer_bounty: class.transfer_bounty,
+            verification_bounty: class.verification_bounty,
+            stake: class.stake,
+            payout_asset: PayoutAsset::Token(token::DEFAULT_ASSET),
+            bounty_vesting: None,
+            expiry: class.expiry,
+            description: class.description,
+            ricardian_contract: class.ricardian_contract,
+            creator: class.creator,
+            created_on: class.created_on,
+            funded: class.funded,
+            funded_on: class.funded_on,
+            funding_period: class.funding_period,
+        }
+    }
+}
+
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Encode, Decode, Default, Clone, PartialEq, Eq)]
+pub struct ClassTemplate<Hash, Balance, Moment, AccountId, BlockNumber> {
+    name: Hash,
+    total_supply: Balance,
+    beneficiary_credentials: Vec<(AccountId, u16)>,
+    // topics the beneficiary needs a claim on from any issuer trusted for the topic
+    beneficiary_topics: Vec<u16>,
+    verifier_credentials: Vec<(AccountId, u16)>,
+    transfer_bounty: Balance,
+    verification_bounty: Balance,
+    stake: Balance,
+    payout_asset: PayoutAsset,
+    bounty_vesting: Option<BountyVesting>,
+    validity: Moment,
+    description: Hash,
+    ricardian_contract: Hash,
+    funding_period: Moment,
+    creator: AccountId,
+    // number of blocks between automatic instantiations, if scheduled
+    period: Option<BlockNumber>,
+}
+
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+pub enum ContractParty {
+    Funder,
+    Agent,
+}
+
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+pub struct ContractAcceptance<Hash, Moment, AccountId> {
+    party: AccountId,
+    // account that signed the acceptance, differs from the party when an operator acts for an organisation
+    signer: AccountId,
+    role: ContractParty,
+    ricardian_contract: Hash,
+    accepted_on: Moment,
+}
+
 #[cfg_attr(feature = "std", derive(Debug))]
 #[derive(Encode, Decode, Default, Clone, PartialEq, Eq)]
 pub struct Bnft<AccountId> {
@@ -51,9 +177,13 @@ decl_storage! {
         BnftCursor get(bnftCursor): u32;
 
         //Bnft Class storage
-        BnftClasses get(get_bnft_class): map u32 => BnftClass<T::Hash, T::TokenBalance, T::Moment, T::AccountId>;
+        BnftClassRecords get(bnft_class_record): map u32 => Option<BnftClass<T::Hash, T::TokenBalance, T::Moment, T::AccountId>>;
         RemainingBnftsForClass get(remaining_bnfts_for): map u32 => u64;
 
+        //Classes stored before payout assets, bounty vesting and topics were added,
+        //read through `get_bnft_class` and moved to BnftClassRecords the first time they change, see `migrate_bnft_class`
+        BnftClasses get(legacy_bnft_class): map u32 => Option<LegacyBnftClass<T::Hash, T::TokenBalance, T::Moment, T::AccountId>>;
+
         //Issued Bnft Storage
         Bnfts get(get_bnft): map (T::AccountId, u32) => Bnft<T::AccountId>; 
         BnftIndex get(get_bnft_index_for): map (T::AccountId, u32) => u32;
@@ -66,6 +196,21 @@ decl_storage! {
         
         //Funders storage
         BnftClassFunder get(funder_of): map u32 => T::AccountId;
+
+        //Native funds reserved for each stake and class funding,
+        //so reserves held for anything else, such as document deposits, are never paid out
+        NativeHeld get(native_held): map token::LockId<T::AccountId> => T::TokenBalance;
+
+        //Ricardian contract acceptance storage, keyed by class, account, role and the party the account accepted for
+        ContractAcceptances get(acceptance_of): map (u32, T::AccountId, ContractParty, T::AccountId) => Option<ContractAcceptance<T::Hash, T::Moment, T::AccountId>>;
+        ContractSignatories get(signatories_of): map u32 => Vec<(T::AccountId, ContractParty, T::AccountId)>;
+
+        //Class template storage
+        TemplateCursor get(templateCursor): u32;
+        ClassTemplates get(get_class_template): map u32 => ClassTemplate<T::Hash, T::TokenBalance, T::Moment, T::AccountId, T::BlockNumber>;
+        TemplateOfClass get(template_of): map u32 => Option<u32>;
+        ClassesForTemplate get(classes_for_template): map u32 => Vec<u32>;
+        ScheduledTemplates get(scheduled_templates): map T::BlockNumber => Vec<u32>;
     }
 }
 
@@ -75,10 +220,15 @@ decl_event! {
         Balance = <T as token::Trait>::TokenBalance,
         Hash = <T as system::Trait>::Hash,
         Moment = <T as timestamp::Trait>::Moment,
+        BlockNumber = <T as system::Trait>::BlockNumber,
     {
         BnftClassCreated(u32, BnftClass<Hash, Balance, Moment, AccountId>),
+        ClassTemplateCreated(u32, ClassTemplate<Hash, Balance, Moment, AccountId, BlockNumber>),
+        ClassTemplateInstantiated(u32, u32),
+        ClassTemplateUnscheduled(u32),
         BnftClassFunded(u32, AccountId, BnftClass<Hash, Balance, Moment, AccountId>),
         BnftIssued(AccountId, Bnft<AccountId>),  
+        ContractAccepted(u32, AccountId, AccountId, Hash),
         BnftVerified(AccountId, AccountId, Bnft<AccountId>),
     }
 }
@@ -98,10 +248,13 @@ decl_module! {
                              name: T::Hash, 
                              total_supply: u64,
                              beneficiary_credentials: Vec<(T::AccountId, u16)>,
+                             beneficiary_topics: Vec<u16>,
                              verifier_credentials: Vec<(T::AccountId, u16)>,
                              transfer_bounty: u64,
                              verification_bounty: u64,
                              stake: u64,
+                             payout_asset: PayoutAsset,
+                             bounty_vesting: Option<BountyVesting>,
                              validity: T::Moment,
                              description: T::Hash,
                              ricardian_contract: T::Hash,
@@ -111,9 +264,9 @@ decl_module! {
             
             //Ensure name is unique
 
-            //Generate id for new bnft
-            let mut classCursor = Self::classCursor();
-            
+            //Ensure required topics are registered
+            Self::ensure_topics_registered(&beneficiary_topics)?;
+
             //Get creation time
             let now = <timestamp::Module<T>>::get();
             
@@ -124,10 +277,13 @@ decl_module! {
                 name: name.clone(),
                 total_supply: <T::TokenBalance as As<u64>>::sa(total_supply),
                 beneficiary_credentials,
+                beneficiary_topics,
                 verifier_credentials,
                 transfer_bounty: <T::TokenBalance as As<u64>>::sa(transfer_bounty),
                 verification_bounty: <T::TokenBalance as As<u64>>::sa(verification_bounty),
                 stake: <T::TokenBalance as As<u64>>::sa(stake),
+                payout_asset,
+                bounty_vesting,
                 expiry,
                 description,
                 ricardian_contract,
@@ -140,22 +296,145 @@ decl_module! {
 
             //Transfer payment for creation    
 
-            //Save BnftClass, remaining supply, classCursor
-            <BnftClasses<T>>::insert(classCursor, bnft_class.clone());
-            <RemainingBnftsForClass<T>>::insert(classCursor, total_supply);
+            Self::insert_bnft_class(bnft_class)?;
+
+            Ok(())
+        }
+
+        fn create_template(origin,
+                           name: T::Hash,
+                           total_supply: u64,
+                           beneficiary_credentials: Vec<(T::AccountId, u16)>,
+                           beneficiary_topics: Vec<u16>,
+                           verifier_credentials: Vec<(T::AccountId, u16)>,
+                           transfer_bounty: u64,
+                           verification_bounty: u64,
+                           stake: u64,
+                           payout_asset: PayoutAsset,
+                           bounty_vesting: Option<BountyVesting>,
+                           validity: T::Moment,
+                           description: T::Hash,
+                           ricardian_contract: T::Hash,
+                           funding_period: T::Moment,
+                           period: Option<T::BlockNumber>) -> Result {
+            //Ensure signed
+            let sender = ensure_signed(origin)?;
+
+            //Ensure schedule is sensible
+            if let Some(period) = period {
+                ensure!(!period.is_zero(), "Template period must be greater than zero");
+            }
+
+            //Ensure required topics are registered
+            Self::ensure_topics_registered(&beneficiary_topics)?;
+
+            //Ensure documents are noted by the creator
+            Self::ensure_documents_noted(&description, &ricardian_contract, &sender)?;
+
+            let template = ClassTemplate {
+                name,
+                total_supply: <T::TokenBalance as As<u64>>::sa(total_supply),
+                beneficiary_credentials,
+                beneficiary_topics,
+                verifier_credentials,
+                transfer_bounty: <T::TokenBalance as As<u64>>::sa(transfer_bounty),
+                verification_bounty: <T::TokenBalance as As<u64>>::sa(verification_bounty),
+                stake: <T::TokenBalance as As<u64>>::sa(stake),
+                payout_asset,
+                bounty_vesting,
+                validity,
+                description,
+                ricardian_contract,
+                funding_period,
+                creator: sender,
+                period,
+            };
+
+            //Save template
+            let templateCursor = Self::templateCursor();
+            <ClassTemplates<T>>::insert(templateCursor, template.clone());
+
+            //Schedule first automatic instantiation
+            if let Some(period) = period {
+                let now = <system::Module<T>>::block_number();
+                let next = now.checked_add(&period).ok_or("Overflow when scheduling template")?;
+                Self::schedule_template(next, templateCursor)?;
+            }
 
             //Emit event
-            Self::deposit_event(RawEvent::BnftClassCreated(classCursor, bnft_class));
+            Self::deposit_event(RawEvent::ClassTemplateCreated(templateCursor, template));
+
+            //Increment templateCursor
+            <TemplateCursor<T>>::put(templateCursor.wrapping_add(1));
+
+            Ok(())
+        }
+
+        fn instantiate_template(origin, template_index: u32) -> Result {
+            //Ensure signed
+            let sender = ensure_signed(origin)?;
 
-            //Increment classCursor
-            let classCursor = classCursor.wrapping_add(1);
-            <ClassCursor<T>>::put(classCursor);
+            //Ensure template exists
+            ensure!(template_index < Self::templateCursor(), "Class template does not exist!");
+
+            //Ensure sender created the template
+            let template = Self::get_class_template(template_index);
+            ensure!(template.creator == sender, "Only the template creator can instantiate it");
+
+            Self::instantiate(template_index, template)?;
+
+            Ok(())
+        }
+
+        fn unschedule_template(origin, template_index: u32) -> Result {
+            //Ensure signed
+            let sender = ensure_signed(origin)?;
+
+            //Ensure template exists and is scheduled
+            ensure!(template_index < Self::templateCursor(), "Class template does not exist!");
+            let mut template = Self::get_class_template(template_index);
+            ensure!(template.creator == sender, "Only the template creator can unschedule it");
+            ensure!(template.period.is_some(), "Class template is not scheduled");
+
+            //Pending entries in ScheduledTemplates are dropped when their block is reached
+            template.period = None;
+            <ClassTemplates<T>>::insert(template_index, template);
+
+            //Emit event
+            Self::deposit_event(RawEvent::ClassTemplateUnscheduled(template_index));
 
             Ok(())
         }
 
+        fn on_initialize(n: T::BlockNumber) {
+            //Instantiate templates scheduled for this block
+            for template_index in <ScheduledTemplates<T>>::take(n) {
+                let template = Self::get_class_template(template_index);
+                let period = match template.period {
+                    Some(period) => period,
+                    None => continue,
+                };
+
+                //A failed instantiation is skipped for this round, but the schedule is kept
+                let _ = Self::instantiate(template_index, template.clone());
+
+                //A template that cannot be rescheduled stops recurring
+                let rescheduled = match n.checked_add(&period) {
+                    Some(next) => Self::schedule_template(next, template_index).is_ok(),
+                    None => false,
+                };
+                if !rescheduled {
+                    let mut template = template;
+                    template.period = None;
+                    <ClassTemplates<T>>::insert(template_index, template);
+                    Self::deposit_event(RawEvent::ClassTemplateUnscheduled(template_index));
+                }
+            }
+        }
+
         fn fund_bnft_class(origin,
-                           class_index: u32) -> Result {
+                           class_index: u32,
+                           accepted_contract: T::Hash) -> Result {
             //Ensure Signed
             let sender = ensure_signed(origin)?;
             
@@ -164,6 +443,7 @@ decl_module! {
             ensure!(class_index < classCursor, "BNFT Class does not exist!"); 
 
             //Ensure not funded already
+            Self::migrate_bnft_class(class_index);
             let mut bnftClass = Self::get_bnft_class(class_index);
             ensure!(!bnftClass.funded, "BNFT Class is already funded");
 
@@ -172,6 +452,9 @@ decl_module! {
             let fundingDeadline = bnftClass.created_on.checked_add(&bnftClass.funding_period).ok_or("Error!")?;
             ensure!(fundingDeadline > now, "BnftClass has expired!");
 
+            //Ensure funder accepts the class's ricardian contract
+            ensure!(accepted_contract == bnftClass.ricardian_contract, "Accepted contract does not match the class's ricardian contract");
+
             //Transfer funds
             let transfer_bounty = bnftClass.transfer_bounty;
             let verification_bounty = bnftClass.verification_bounty;
@@ -179,12 +462,13 @@ decl_module! {
             let total_transfer_bounty = transfer_bounty.checked_mul(&total_supply).ok_or("Overflow")?;
             let total_verification_bounty = verification_bounty.checked_mul(&total_supply).ok_or("Overflow")?;
             let amount = total_transfer_bounty.checked_add(&total_verification_bounty).ok_or("Overflow")?;
-            <token::Module<T>>::lock(sender.clone(), amount, (sender.clone(), class_index));
+            Self::lock_payout(bnftClass.payout_asset, sender.clone(), amount, token::LockId::Funding(sender.clone(), class_index))?;
             
             //Update storage
             bnftClass.funded = true;
-            <BnftClasses<T>>::insert(class_index, bnftClass.clone());
+            <BnftClassRecords<T>>::insert(class_index, bnftClass.clone());
             <BnftClassFunder<T>>::insert(class_index, sender.clone());
+            Self::record_acceptance(class_index, sender.clone(), sender.clone(), ContractParty::Funder, accepted_contract, now);
 
             //Emit event
             Self::deposit_event(RawEvent::BnftClassFunded(class_index, sender, bnftClass));
@@ -194,9 +478,14 @@ decl_module! {
 
         fn issue_bnft(origin, 
                       class_index: u32, 
-                      uri: T::AccountId) -> Result {
+                      uri: T::AccountId,
+                      accepted_contract: T::Hash,
+                      on_behalf_of: Option<T::AccountId>) -> Result {
             //Ensure Signed
-            let sender = ensure_signed(origin)?;
+            let operator = ensure_signed(origin)?;
+
+            //Resolve agent, either the signer or the organisation it holds an action key for
+            let sender = Self::acting_account(operator.clone(), on_behalf_of)?;
 
             //Ensure bnft class exists
             let classCursor = Self::classCursor();
@@ -206,22 +495,28 @@ decl_module! {
             let bnftClass = Self::get_bnft_class(class_index);
             ensure!(bnftClass.funded, "BNFT class is not yet funded!");
 
+            //Ensure agent accepts the class's ricardian contract
+            ensure!(accepted_contract == bnftClass.ricardian_contract, "Accepted contract does not match the class's ricardian contract");
+
             // Ensure uri is unique
             let uriClassIndexTuple = (uri.clone(), class_index);
             ensure!(!<Bnfts<T>>::exists(&uriClassIndexTuple), "Bnft already issued");
 
             //Ensure beneficiary has correct credential
-            ensure!(&bnftClass.beneficiary_credentials.len() < &6, "Too many credentials to check!");
+            ensure!(&(bnftClass.beneficiary_credentials.len() + bnftClass.beneficiary_topics.len()) < &6, "Too many credentials to check!");
             for required_credential_tuple in bnftClass.beneficiary_credentials {
                 ensure!(Self::claim_is_valid(required_credential_tuple, uri.clone()), "Invalid Claim");
             }
+            for topic in bnftClass.beneficiary_topics {
+                ensure!(Self::topic_is_satisfied(topic, uri.clone()), "No valid claim from a trusted issuer");
+            }
 
             //Ensure total supply has not been exceeded
             let remainingBnftsForClass = Self::remaining_bnfts_for(class_index);
             ensure!(remainingBnftsForClass > 0, "All BNFTs have been issued for this class");
 
             // Lock stake
-            <token::Module<T>>::lock(sender.clone(), bnftClass.stake, uriClassIndexTuple.clone())?;
+            Self::lock_payout(bnftClass.payout_asset, sender.clone(), bnftClass.stake, token::LockId::Stake(uri.clone(), class_index))?;
 
             // Create bnft
             let bnft = Bnft {
@@ -248,6 +543,10 @@ decl_module! {
             //Decrement remaining Bnfts for class
             <RemainingBnftsForClass<T>>::insert(class_index, remainingBnftsForClass.clone() - 1);
 
+            //Record agent's acceptance of the contract
+            let now = <timestamp::Module<T>>::get();
+            Self::record_acceptance(class_index, sender.clone(), operator, ContractParty::Agent, accepted_contract, now);
+
             // Emit event
             Self::deposit_event(RawEvent::BnftIssued(sender, bnft));
 
@@ -257,19 +556,38 @@ decl_module! {
         fn verifyAndBurn(origin,
                          agent: T::AccountId,
                          class_index: u32,
-                         uri: T::AccountId) -> Result {
+                         uri: T::AccountId,
+                         on_behalf_of: Option<T::AccountId>) -> Result {
             //Ensure signed
-            let sender = ensure_signed(origin)?;
+            let operator = ensure_signed(origin)?;
+
+            //Resolve verifier, either the signer or the organisation it holds an action key for
+            let sender = Self::acting_account(operator, on_behalf_of)?;
 
             //Ensure BNFT exists
             let uriClassIndexTuple = (uri.clone(), class_index);
             ensure!(<Bnfts<T>>::exists(uriClassIndexTuple.clone()), "Bnft does not exist or is already verified");
 
             //Ensure Agent owns BNFT
-            ensure!(Self::owner_of(uriClassIndexTuple.clone()).unwrap() == agent, "Agent does not own BNFT");   
+            ensure!(Self::owner_of(uriClassIndexTuple.clone()) == Some(agent.clone()), "Agent does not own BNFT");   
 
             //Verify verifier has required credential
 
+            //Ensure beneficiary is still eligible, claims may have been revoked, removed or expired since issuance
+            let bnftClass = Self::get_bnft_class(class_index);
+            for required_credential_tuple in bnftClass.beneficiary_credentials.clone() {
+                ensure!(Self::claim_is_valid(required_credential_tuple, uri.clone()), "Beneficiary is no longer eligible");
+            }
+            for topic in bnftClass.beneficiary_topics.clone() {
+                ensure!(Self::topic_is_satisfied(topic, uri.clone()), "Beneficiary is no longer eligible");
+            }
+
+            //Ensure stake and bounties can be released before changing any storage
+            let funder = Self::funder_of(class_index);
+            let total_bounty = bnftClass.transfer_bounty.checked_add(&bnftClass.verification_bounty).ok_or("Overflow")?;
+            Self::ensure_releasable(bnftClass.payout_asset, agent.clone(), vec![agent.clone()], bnftClass.stake, token::LockId::Stake(uri.clone(), class_index))?;
+            Self::ensure_releasable(bnftClass.payout_asset, funder.clone(), vec![agent.clone(), sender.clone()], total_bounty, token::LockId::Funding(funder.clone(), class_index))?;
+            Self::ensure_bounties_vestable(bnftClass.payout_asset, bnftClass.bounty_vesting, vec![agent.clone(), sender.clone()])?;
             
             //Remove from Bnfts
             let mut bnft = Self::get_bnft(uriClassIndexTuple.clone());
@@ -289,13 +607,11 @@ decl_module! {
             <OwnedBnftsCount<T>>::insert(&agent, ownedBnftsCount.saturating_sub(1));
 
             //Release stake
-            let bnftClass = Self::get_bnft_class(class_index);
-            <token::Module<T>>::unlock(agent.clone(), bnftClass.stake, uriClassIndexTuple.clone()); 
+            Self::release_payout(bnftClass.payout_asset, agent.clone(), agent.clone(), bnftClass.stake, token::LockId::Stake(uri.clone(), class_index))?;
 
             //Transfer bounty to agent and verifier
-            let funder = Self::funder_of(class_index);
-            <token::Module<T>>::unlock(agent.clone(), bnftClass.transfer_bounty, (funder.clone(), class_index));
-            <token::Module<T>>::unlock(sender.clone(), bnftClass.verification_bounty, (funder.clone(), class_index));
+            Self::release_bounty(bnftClass.payout_asset, bnftClass.bounty_vesting, funder.clone(), agent.clone(), bnftClass.transfer_bounty, token::LockId::Funding(funder.clone(), class_index))?;
+            Self::release_bounty(bnftClass.payout_asset, bnftClass.bounty_vesting, funder.clone(), sender.clone(), bnftClass.verification_bounty, token::LockId::Funding(funder.clone(), class_index))?;
 
             //Emit events
             Self::deposit_event(RawEvent::BnftVerified(sender, agent, bnft));
@@ -306,6 +622,256 @@ decl_module! {
 }
 
 impl<T: Trait> Module<T> {
+    // stores a new bnft class under the next class index and returns that index
+    fn insert_bnft_class(bnft_class: BnftClass<T::Hash, T::TokenBalance, T::Moment, T::AccountId>) -> rstd::result::Result<u32, &'static str> {
+        let classCursor = Self::classCursor();
+
+        //Ensure the creator noted both documents before referencing either
+        Self::ensure_documents_noted(&bnft_class.description, &bnft_class.ricardian_contract, &bnft_class.creator)?;
+
+        //Keep documents available for the lifetime of the class
+        <preimage::Module<T>>::add_reference(bnft_class.description, &bnft_class.creator)?;
+        <preimage::Module<T>>::add_reference(bnft_class.ricardian_contract, &bnft_class.creator)?;
+
+        //Save BnftClass, remaining supply, classCursor
+        <BnftClassRecords<T>>::insert(classCursor, bnft_class.clone());
+        <RemainingBnftsForClass<T>>::insert(classCursor, <T::TokenBalance as As<u64>>::as_(bnft_class.total_supply));
+
+        //Emit event
+        Self::deposit_event(RawEvent::BnftClassCreated(classCursor, bnft_class));
+
+        //Increment classCursor
+        <ClassCursor<T>>::put(classCursor.wrapping_add(1));
+
+        Ok(classCursor)
+    }
+
+    // returns a class in the current layout, converting classes stored before the layout changed
+    pub fn get_bnft_class(class_index: u32) -> BnftClass<T::Hash, T::TokenBalance, T::Moment, T::AccountId> {
+        Self::bnft_class_record(class_index)
+            .or_else(|| Self::legacy_bnft_class(class_index).map(Into::into))
+            .unwrap_or_default()
+    }
+
+    // moves a class stored before the layout changed to BnftClassRecords
+    fn migrate_bnft_class(class_index: u32) {
+        if let Some(class) = <BnftClasses<T>>::take(class_index) {
+            <BnftClassRecords<T>>::insert(class_index, BnftClass::from(class));
+        }
+    }
+
+    // a class can only pin documents whose deposit its creator paid
+    fn ensure_documents_noted(description: &T::Hash, ricardian_contract: &T::Hash, creator: &T::AccountId) -> Result {
+        ensure!(<preimage::Module<T>>::is_noted_by(description, creator), "Description must be noted by the class creator");
+        ensure!(<preimage::Module<T>>::is_noted_by(ricardian_contract, creator), "Ricardian contract must be noted by the class creator");
+        Ok(())
+    }
+
+    // queues a template for instantiation at the first block from `at` with room left,
+    // trying at most MAX_SCHEDULE_DELAY later blocks
+    fn schedule_template(at: T::BlockNumber, template_index: u32) -> Result {
+        let mut block = at;
+        for _ in 0..=MAX_SCHEDULE_DELAY {
+            if Self::scheduled_templates(block).len() < MAX_SCHEDULED_TEMPLATES_PER_BLOCK {
+                <ScheduledTemplates<T>>::mutate(block, |templates| templates.push(template_index));
+                return Ok(());
+            }
+            block = block.checked_add(&<T::BlockNumber as As<u64>>::sa(1)).ok_or("Overflow when scheduling template")?;
+        }
+
+        Err("Too many templates scheduled around this block")
+    }
+
+    // creates a new bnft class from a template and links it back to the template
+    fn instantiate(template_index: u32,
+                   template: ClassTemplate<T::Hash, T::TokenBalance, T::Moment, T::AccountId, T::BlockNumber>) -> Result {
+        //Get creation time
+        let now = <timestamp::Module<T>>::get();
+
+        //Calculate expiry
+        let expiry = now.checked_add(&template.validity).ok_or("Overflow when setting expiry")?;
+
+        let bnft_class = BnftClass {
+            name: template.name,
+            total_supply: template.total_supply,
+            beneficiary_credentials: template.beneficiary_credentials,
+            beneficiary_topics: template.beneficiary_topics,
+            verifier_credentials: template.verifier_credentials,
+            transfer_bounty: template.transfer_bounty,
+            verification_bounty: template.verification_bounty,
+            stake: template.stake,
+            payout_asset: template.payout_asset,
+            bounty_vesting: template.bounty_vesting,
+            expiry,
+            description: template.description,
+            ricardian_contract: template.ricardian_contract,
+            creator: template.creator,
+            created_on: now,
+            funded: false,
+            funded_on: None,
+            funding_period: template.funding_period,
+        };
+
+        let class_index = Self::insert_bnft_class(bnft_class)?;
+
+        //Link instance to template
+        <TemplateOfClass<T>>::insert(class_index, template_index);
+        <ClassesForTemplate<T>>::mutate(template_index, |classes| classes.push(class_index));
+
+        //Emit event
+        Self::deposit_event(RawEvent::ClassTemplateInstantiated(template_index, class_index));
+
+        Ok(())
+    }
+
+    // returns the ricardian contract of a class, if the document has been noted
+    pub fn ricardian_contract_of(class_index: u32) -> Option<Vec<u8>> {
+        if class_index >= Self::classCursor() {
+            return None;
+        }
+        <preimage::Module<T>>::preimage(Self::get_bnft_class(class_index).ricardian_contract)
+    }
+
+    // returns the description of a class, if the document has been noted
+    pub fn description_of(class_index: u32) -> Option<Vec<u8>> {
+        if class_index >= Self::classCursor() {
+            return None;
+        }
+        <preimage::Module<T>>::preimage(Self::get_bnft_class(class_index).description)
+    }
+
+    // stores the first acceptance of a class's ricardian contract by a party in a role
+    // an operator signing for an organisation gets an entry of its own for each organisation,
+    // so each operator can be cited, and a funder who later issues also gets an agent entry
+    fn record_acceptance(class_index: u32, party: T::AccountId, signer: T::AccountId, role: ContractParty, ricardian_contract: T::Hash, now: T::Moment) {
+        let mut accounts = vec![party.clone()];
+        if signer != party {
+            accounts.push(signer.clone());
+        }
+
+        for account in accounts {
+            let acceptanceKey = (class_index, account.clone(), role.clone(), party.clone());
+            if <ContractAcceptances<T>>::exists(&acceptanceKey) {
+                continue;
+            }
+
+            let acceptance = ContractAcceptance {
+                party: party.clone(),
+                signer: signer.clone(),
+                role: role.clone(),
+                ricardian_contract: ricardian_contract.clone(),
+                accepted_on: now.clone(),
+            };
+            <ContractAcceptances<T>>::insert(&acceptanceKey, acceptance);
+            <ContractSignatories<T>>::mutate(class_index, |signatories| signatories.push((account.clone(), role.clone(), party.clone())));
+
+            Self::deposit_event(RawEvent::ContractAccepted(class_index, party.clone(), signer.clone(), ricardian_contract.clone()));
+        }
+    }
+
+    // locks `amount` of `who`'s funds in the given payout asset
+    // token deposits are tracked under `lock_id`, native funds are reserved on `who`'s account
+    fn lock_payout(asset: PayoutAsset, who: T::AccountId, amount: T::TokenBalance, lock_id: token::LockId<T::AccountId>) -> Result {
+        match asset {
+            PayoutAsset::Token(asset_id) => <token::Module<T>>::lock(asset_id, who, amount, lock_id).map_err(Into::into),
+            PayoutAsset::Native => {
+                let held = Self::native_held(&lock_id).checked_add(&amount).ok_or("Overflow")?;
+                T::NativeCurrency::reserve(&who, amount)?;
+                <NativeHeld<T>>::insert(&lock_id, held);
+                Ok(())
+            },
+        }
+    }
+
+    // releases `amount` previously locked by `owner` to `to` in the given payout asset
+    fn release_payout(asset: PayoutAsset, owner: T::AccountId, to: T::AccountId, amount: T::TokenBalance, lock_id: token::LockId<T::AccountId>) -> Result {
+        match asset {
+            PayoutAsset::Token(asset_id) => <token::Module<T>>::unlock(asset_id, owner, to, amount, lock_id).map_err(Into::into),
+            PayoutAsset::Native => {
+                let held = Self::native_held(&lock_id).checked_sub(&amount).ok_or("Not enough reserved balance")?;
+                if owner == to {
+                    T::NativeCurrency::unreserve(&owner, amount);
+                } else {
+                    T::NativeCurrency::repatriate_reserved(&owner, &to, amount)?;
+                }
+                if held.is_zero() {
+                    <NativeHeld<T>>::remove(&lock_id);
+                } else {
+                    <NativeHeld<T>>::insert(&lock_id, held);
+                }
+                Ok(())
+            },
+        }
+    }
+
+    // releases a bounty, as a vesting schedule if the class asks for one
+    // native bounties are always paid as lump sums
+    fn release_bounty(asset: PayoutAsset,
+                      vesting: Option<BountyVesting>,
+                      funder: T::AccountId,
+                      to: T::AccountId,
+                      amount: T::TokenBalance,
+                      lock_id: token::LockId<T::AccountId>) -> Result {
+        match (asset, vesting) {
+            (PayoutAsset::Token(asset_id), Some(vesting)) if vesting.duration > 0 => {
+                //Spread the bounty over the vesting duration, rounding up so it fully matures
+                let duration = <T::TokenBalance as As<u64>>::sa(vesting.duration as u64);
+                let per_block = amount.checked_add(&duration).ok_or("Overflow")?.saturating_sub(<T::TokenBalance as As<u64>>::sa(1)) / duration;
+                let now = <system::Module<T>>::block_number();
+                let cliff = now.checked_add(&<T::BlockNumber as As<u64>>::sa(vesting.cliff as u64)).ok_or("Overflow")?;
+                <token::Module<T>>::unlock_vesting(asset_id, funder, to, amount, lock_id, per_block, cliff).map_err(Into::into)
+            },
+            _ => Self::release_payout(asset, funder, to, amount, lock_id),
+        }
+    }
+
+    // checks that `amount` locked by `owner` can be released to `recipients` in the given payout asset
+    // native funds can only be moved to accounts that already exist
+    fn ensure_releasable(asset: PayoutAsset,
+                         owner: T::AccountId,
+                         recipients: Vec<T::AccountId>,
+                         amount: T::TokenBalance,
+                         lock_id: token::LockId<T::AccountId>) -> Result {
+        match asset {
+            PayoutAsset::Token(asset_id) => <token::Module<T>>::ensure_locked(asset_id, owner, amount, lock_id).map_err(Into::into),
+            PayoutAsset::Native => {
+                ensure!(Self::native_held(&lock_id) >= amount, "Not enough reserved balance");
+                ensure!(T::NativeCurrency::reserved_balance(&owner) >= amount, "Not enough reserved balance");
+                for to in recipients {
+                    ensure!(to == owner || !T::NativeCurrency::total_balance(&to).is_zero(), "Recipient account does not exist");
+                }
+                Ok(())
+            },
+        }
+    }
+
+    // checks that every recipient can be granted a vesting schedule for its bounty
+    // a recipient listed twice needs room for two schedules
+    fn ensure_bounties_vestable(asset: PayoutAsset, vesting: Option<BountyVesting>, recipients: Vec<T::AccountId>) -> Result {
+        match (asset, vesting) {
+            (PayoutAsset::Token(_), Some(vesting)) if vesting.duration > 0 => {
+                for to in recipients.iter() {
+                    let count = recipients.iter().filter(|other| *other == to).count();
+                    <token::Module<T>>::ensure_can_vest(to, count).map_err(<&'static str>::from)?;
+                }
+                Ok(())
+            },
+            _ => Ok(()),
+        }
+    }
+
+    // returns the account an extrinsic acts for
+    // an operator may act for an organisation that registered it as an action key
+    fn acting_account(operator: T::AccountId, on_behalf_of: Option<T::AccountId>) -> rstd::result::Result<T::AccountId, &'static str> {
+        match on_behalf_of {
+            Some(organisation) => {
+                ensure!(<id::Module<T>>::keyHasPurpose(organisation.clone(), operator, id::ACTION_KEY), "Operator has no action key for this organisation");
+                Ok(organisation)
+            },
+            None => Ok(operator),
+        }
+    }
+
     fn claim_is_valid(required_credential_tuple: (T::AccountId, u16), uri: T::AccountId) -> bool {
         let issuer_bytes = required_credential_tuple.0.encode();
         let topic_bytes = required_credential_tuple.1.encode();
@@ -320,8 +886,32 @@ impl<T: Trait> Module<T> {
         }
     }
 
+    // checks for a valid claim on the topic from any issuer currently trusted for it
+    fn topic_is_satisfied(topic: u16, uri: T::AccountId) -> bool {
+        <id::Module<T>>::trustedIssuers(topic)
+            .into_iter()
+            .any(|issuer| Self::claim_is_valid((issuer, topic), uri.clone()))
+    }
+
+    fn ensure_topics_registered(topics: &Vec<u16>) -> Result {
+        for topic in topics {
+            ensure!(<id::Module<T>>::getTopic(*topic).is_some(), "Required topic is not registered");
+        }
+        Ok(())
+    }
+
+    // checks that a claim is not revoked and that its current validity window includes this block
+    // issuer and topic need no check, they are part of the claimId and cannot change
     fn validate_claim(claimId: Vec<u8>) -> bool {
-        true
+        if <id::Module<T>>::isRevoked(claimId.clone()) {
+            return false;
+        }
+        let claim = match <id::Module<T>>::getClaim(claimId) {
+            Some(claim) => claim,
+            None => return false,
+        };
+        let now = <system::Module<T>>::block_number();
+        claim.is_valid_at(&now)
     }
 }
 
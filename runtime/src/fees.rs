@@ -0,0 +1,101 @@
+// runtime module letting accounts pay transaction fees in a token module asset
+// fees are computed in native balance as usual and converted at an exchange rate set by root
+// accounts that have not chosen a fee asset keep paying in native balance
+
+use support::{StorageMap, ensure, dispatch::Result, decl_module, decl_storage, decl_event};
+use runtime_primitives::traits::{As, CheckedAdd, CheckedMul, MakePayment, Zero};
+use system::{ensure_signed, ensure_root};
+use crate::token::{self, AssetId};
+
+pub trait Trait: balances::Trait + token::Trait {
+    type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+}
+
+decl_storage! {
+    trait Store for Module<T: Trait> as Fees {
+        //Exchange rate of each asset as (token units, native units) worth the same,
+        //so tokens worth more or less than a native unit can both be expressed
+        ExchangeRate get(exchange_rate): map AssetId => Option<(T::TokenBalance, T::TokenBalance)>;
+
+        //Asset an account pays its fees in
+        FeeAsset get(fee_asset_of): map T::AccountId => Option<AssetId>;
+    }
+}
+
+decl_event! {
+    pub enum Event<T> where
+        AccountId = <T as system::Trait>::AccountId,
+        TokenBalance = <T as token::Trait>::TokenBalance,
+    {
+        ExchangeRateChanged(AssetId, Option<(TokenBalance, TokenBalance)>),
+        FeeAssetChanged(AccountId, Option<AssetId>),
+    }
+}
+
+decl_module! {
+    pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+        fn deposit_event<T>() = default;
+
+        fn set_exchange_rate(origin, asset_id: AssetId, rate: Option<(T::TokenBalance, T::TokenBalance)>) -> Result {
+            ensure_root(origin)?;
+
+            match rate {
+                Some(rate) => {
+                    //A zero rate would make transactions free
+                    ensure!(!rate.0.is_zero() && !rate.1.is_zero(), "Exchange rate must be positive");
+                    <ExchangeRate<T>>::insert(asset_id, rate);
+                },
+                None => <ExchangeRate<T>>::remove(asset_id),
+            }
+
+            //Emit event
+            Self::deposit_event(RawEvent::ExchangeRateChanged(asset_id, rate));
+
+            Ok(())
+        }
+
+        fn set_fee_asset(origin, asset_id: Option<AssetId>) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            match asset_id {
+                Some(asset_id) => {
+                    ensure!(Self::exchange_rate(asset_id).is_some(), "Fees cannot be paid in this asset");
+                    <FeeAsset<T>>::insert(&sender, asset_id);
+                },
+                None => <FeeAsset<T>>::remove(&sender),
+            }
+
+            //Emit event
+            Self::deposit_event(RawEvent::FeeAssetChanged(sender, asset_id));
+
+            Ok(())
+        }
+    }
+}
+
+impl<T: Trait> MakePayment<T::AccountId> for Module<T> {
+    fn make_payment(who: &T::AccountId, encoded_len: usize) -> Result {
+        //Accounts without a usable fee asset pay in native balance
+        let (asset_id, rate) = match Self::fee_asset_of(who).and_then(|asset_id| Self::exchange_rate(asset_id).map(|rate| (asset_id, rate))) {
+            Some(fee_asset) => fee_asset,
+            None => return <balances::Module<T> as MakePayment<T::AccountId>>::make_payment(who, encoded_len),
+        };
+
+        //Same fee the balances module would charge, converted to the asset
+        let byte_fee = <balances::Module<T>>::transaction_byte_fee()
+            .checked_mul(&<T::Balance as As<u64>>::sa(encoded_len as u64))
+            .ok_or("Overflow when calculating fee")?;
+        let native_fee = <balances::Module<T>>::transaction_base_fee()
+            .checked_add(&byte_fee)
+            .ok_or("Overflow when calculating fee")?;
+        //Rounded up, so a non-zero fee never converts to zero tokens
+        let (tokens, native) = rate;
+        let fee = <T::TokenBalance as As<u64>>::sa(native_fee.as_())
+            .checked_mul(&tokens)
+            .and_then(|fee| fee.checked_add(&native))
+            .ok_or("Overflow when calculating fee")?;
+        let fee = (fee - <T::TokenBalance as As<u64>>::sa(1)) / native;
+
+        <token::Module<T>>::charge_fee(asset_id, who.clone(), fee)
+    }
+}
@@ -1,27 +1,144 @@
 use parity_codec::{Decode, Encode};
-use support::{StorageValue, StorageMap, ensure, dispatch::Result, decl_module, decl_storage, decl_event};
+use support::{StorageValue, StorageMap, Parameter, ensure, dispatch::{Result, IsSubType}, decl_module, decl_storage, decl_event};
 use support::traits::{Currency, WithdrawReason, ExistenceRequirement};
-use runtime_primitives::traits::{Zero, Hash, Saturating, As, CheckedAdd, CheckedMul, CheckedDiv};
-use {system::ensure_signed, timestamp};
+use runtime_primitives::traits::{Zero, Hash, Saturating, As, CheckedAdd, CheckedMul, CheckedDiv, Dispatchable, Verify};
+use {system::{ensure_signed, ensure_root}, timestamp};
 use rstd::prelude::*;
 use runtime_io::keccak_256;
 use crate::token;
 
 pub trait Trait: balances::Trait + timestamp::Trait + token::Trait {
     type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+    // runtime call an identity can execute once enough of its keys approve
+    type Proposal: Parameter + Dispatchable<Origin = Self::Origin> + IsSubType<Module<Self>>;
 }
 
+//Number of previous versions kept for each claim
+pub const MAX_CLAIM_HISTORY: usize = 10;
+
+//Number of claims removed at a block once expired, later expiries go to the next block with room
+pub const MAX_EXPIRED_CLAIMS_PER_BLOCK: usize = 20;
+
+//Number of blocks after its expiry a claim's removal can be pushed back before adding it fails
+pub const MAX_EXPIRY_DELAY: u64 = 100;
+
+//Number of claims revokeClaimsByTopic revokes at once, call it again for the rest
+pub const MAX_REVOCATIONS_PER_CALL: usize = 50;
+
+//Limits of the topic registry
+pub const MAX_TOPIC_DESCRIPTION: usize = 256;
+pub const MAX_TRUSTED_ISSUERS: usize = 16;
+
+//Number of recovery accounts an identity can name
+pub const MAX_RECOVERY_ACCOUNTS: usize = 10;
+
+//Fewest blocks between initiating and claiming a recovery, about 40 hours at ten second blocks
+pub const MIN_RECOVERY_DELAY: u64 = 14400;
+
+//ERC734 key purposes
+pub const MANAGEMENT_KEY: u16 = 1;
+pub const ACTION_KEY: u16 = 2;
+pub const CLAIM_SIGNER_KEY: u16 = 3;
+
+//Key types, ECDSA as in ERC734 (2 is RSA there, which is not supported)
+//ECDSA keys are stored as 20 byte Ethereum addresses, sr25519 and ed25519 keys as 32 byte public keys
+pub const ECDSA_KEY: u16 = 1;
+pub const SR25519_KEY: u16 = 3;
+pub const ED25519_KEY: u16 = 4;
+
 #[cfg_attr(feature = "std", derive(Debug))]
 #[derive(Encode, Decode, Default, Clone, PartialEq, Eq)]
 pub struct Key<AccountId> {
+    purposes: Vec<u16>,
+    keyType: u16,
+    key: AccountId,
+}
+
+impl<AccountId> Key<AccountId> {
+    //Management keys can act for every purpose, as in ERC734
+    pub fn has_purpose(&self, purpose: u16) -> bool {
+        self.purposes.contains(&purpose) || self.purposes.contains(&MANAGEMENT_KEY)
+    }
+}
+
+//Key layout before keys held several purposes, see `migrateKey`
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Encode, Decode, Default, Clone, PartialEq, Eq)]
+pub struct LegacyKey<AccountId> {
     purpose: u16,
     keyType: u16,
     key: AccountId,
 }
 
+impl<AccountId> From<LegacyKey<AccountId>> for Key<AccountId> {
+    fn from(key: LegacyKey<AccountId>) -> Self {
+        Key {
+            purposes: vec![key.purpose],
+            keyType: key.keyType,
+            key: key.key,
+        }
+    }
+}
+
+//Key that is not an account, it can sign claims but not send transactions
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Encode, Decode, Default, Clone, PartialEq, Eq)]
+pub struct PublicKey {
+    purposes: Vec<u16>,
+    keyType: u16,
+    key: Vec<u8>,
+}
+
+impl PublicKey {
+    //Management keys can act for every purpose, as in ERC734
+    pub fn has_purpose(&self, purpose: u16) -> bool {
+        self.purposes.contains(&purpose) || self.purposes.contains(&MANAGEMENT_KEY)
+    }
+}
+
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+pub struct Execution<AccountId, Proposal> {
+    identity: AccountId,
+    purpose: u16,
+    call: Proposal,
+    approvals: Vec<AccountId>,
+}
+
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Encode, Decode, Default, Clone, PartialEq, Eq)]
+pub struct Claim<AccountId, BlockNumber> {
+    topic: u16,
+    scheme: u16,
+    issuer: AccountId,
+    signature: Vec<u8>,
+    data: Vec<u8>,
+    uri: Vec<u8>,
+    valid_from: Option<BlockNumber>,
+    valid_until: Option<BlockNumber>,
+}
+
+impl<AccountId, BlockNumber: PartialOrd> Claim<AccountId, BlockNumber> {
+    pub fn topic(&self) -> u16 {
+        self.topic
+    }
+
+    pub fn issuer(&self) -> &AccountId {
+        &self.issuer
+    }
+
+    // a claim is valid from `valid_from` up to, but excluding, `valid_until`
+    pub fn is_valid_at(&self, now: &BlockNumber) -> bool {
+        let started = self.valid_from.as_ref().map_or(true, |from| from <= now);
+        let expired = self.valid_until.as_ref().map_or(false, |until| until <= now);
+        started && !expired
+    }
+}
+
+//Claim layout before validity windows were added, see `migrateClaim`
 #[cfg_attr(feature = "std", derive(Debug))]
 #[derive(Encode, Decode, Default, Clone, PartialEq, Eq)]
-pub struct Claim<AccountId> {
+pub struct LegacyClaim<AccountId> {
     topic: u16,
     scheme: u16,
     issuer: AccountId,
@@ -30,34 +147,195 @@ pub struct Claim<AccountId> {
     uri: Vec<u8>,
 }
 
+//Existing claims never expire
+impl<AccountId, BlockNumber> From<LegacyClaim<AccountId>> for Claim<AccountId, BlockNumber> {
+    fn from(claim: LegacyClaim<AccountId>) -> Self {
+        Claim {
+            topic: claim.topic,
+            scheme: claim.scheme,
+            issuer: claim.issuer,
+            signature: claim.signature,
+            data: claim.data,
+            uri: claim.uri,
+            valid_from: None,
+            valid_until: None,
+        }
+    }
+}
+
+//Accounts that can together give a new account control of an identity
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Encode, Decode, Default, Clone, PartialEq, Eq)]
+pub struct RecoveryConfig<AccountId, BlockNumber> {
+    recoveryAccounts: Vec<AccountId>,
+    threshold: u16,
+    // blocks between initiation and claim, during which the identity can cancel
+    delay: BlockNumber,
+}
+
+//Recovery in progress for an identity
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Encode, Decode, Default, Clone, PartialEq, Eq)]
+pub struct ActiveRecovery<AccountId, BlockNumber> {
+    rescuer: AccountId,
+    started: BlockNumber,
+    vouchers: Vec<AccountId>,
+    // recovery accounts that want this recovery closed
+    objections: Vec<AccountId>,
+}
+
+//Registered claim topic
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Encode, Decode, Default, Clone, PartialEq, Eq)]
+pub struct Topic<AccountId, Hash> {
+    owner: AccountId,
+    description: Vec<u8>,
+    // hash of the schema claim data on this topic follows
+    schema: Hash,
+}
+
+//Issuer revocation of a claim, revoked claims are kept so it can be checked against them
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Encode, Decode, Default, Clone, PartialEq, Eq)]
+pub struct Revocation<Moment> {
+    reason: u16,
+    revoked_on: Moment,
+}
+
+impl<Moment> Revocation<Moment> {
+    pub fn reason(&self) -> u16 {
+        self.reason
+    }
+
+    pub fn revoked_on(&self) -> &Moment {
+        &self.revoked_on
+    }
+}
+
 decl_storage! {
     trait Store for Module<T: Trait> as Id {
         //Keys Store
-        Keys get(getKeyFor): map (T::AccountId, T::AccountId) => Key<T::AccountId>;
+        KeyRecords get(getKeyFor): map (T::AccountId, T::AccountId) => Option<Key<T::AccountId>>;
         KeysByPurpose get(keysByPurpose): map (T::AccountId, u16) => Vec<T::AccountId>;
+        KeysRequired get(keysRequired): map (T::AccountId, u16) => u16;
+        KeysOf get(keysOf): map T::AccountId => Vec<T::AccountId>;
+        PublicKeys get(getPublicKey): map (T::AccountId, Vec<u8>) => Option<PublicKey>;
+        PublicKeysOf get(publicKeysOf): map T::AccountId => Vec<Vec<u8>>;
+
+        //Execution Store
+        ExecutionNonce get(executionNonce): u32;
+        Executions get(getExecution): map u32 => Option<Execution<T::AccountId, T::Proposal>>;
         
         //Claim Store
-        Claims get(getClaimById): map Vec<u8> => Claim<T::AccountId>;
+        ClaimRecords get(getClaimById): map Vec<u8> => Option<Claim<T::AccountId, T::BlockNumber>>;
         ClaimsByTopic get(getClaimsByTopic): map (T::AccountId, u16) => Vec<Vec<u8>>;
+        ClaimHistory get(claimHistory): map Vec<u8> => Vec<Claim<T::AccountId, T::BlockNumber>>;
+        ClaimsOf get(claimsOf): map T::AccountId => Vec<Vec<u8>>;
+        ClaimsIssuedBy get(claimsIssuedBy): map T::AccountId => Vec<Vec<u8>>;
+        //Number of signed versions submitted for each claimId, part of every claim signature
+        //Kept when the claim is removed so an earlier signature cannot add it again
+        ClaimNonces get(claimNonce): map Vec<u8> => u64;
+
+        //Topic registry
+        Topics get(getTopic): map u16 => Option<Topic<T::AccountId, T::Hash>>;
+        TrustedIssuers get(trustedIssuers): map u16 => Vec<T::AccountId>;
+
+        //Recovery Store
+        RecoveryConfigs get(recoveryConfig): map T::AccountId => Option<RecoveryConfig<T::AccountId, T::BlockNumber>>;
+        ActiveRecoveries get(activeRecovery): map T::AccountId => Option<ActiveRecovery<T::AccountId, T::BlockNumber>>;
+
+        //Keys stored before keys held several purposes,
+        //moved to KeyRecords the first time they are changed or removed, see `migrateKey`
+        Keys get(legacyKey): map (T::AccountId, T::AccountId) => Option<LegacyKey<T::AccountId>>;
+
+        //Revocation registry
+        Revocations get(getRevocation): map Vec<u8> => Option<Revocation<T::Moment>>;
+
+        //Claims due to be removed at a block, as (subject, claimId), at most MAX_EXPIRED_CLAIMS_PER_BLOCK
+        ExpiringClaims get(expiringClaims): map T::BlockNumber => Vec<(T::AccountId, Vec<u8>)>;
+        ExpiringClaimsCount get(expiringClaimsCount): map T::BlockNumber => u32;
+        //Block a claim is scheduled to be removed at, so it can be unscheduled when it changes or is removed
+        ExpiryOf get(expiryBlockOf): map Vec<u8> => Option<T::BlockNumber>;
+
+        //Claims stored before validity windows were added,
+        //moved to ClaimRecords the first time they are changed or removed, see `migrateClaim`
+        Claims get(legacyClaim): map Vec<u8> => Option<LegacyClaim<T::AccountId>>;
     }
 }
 
 decl_event! {
-    pub enum Event<T> where AccountId = <T as system::Trait>::AccountId, {
+    pub enum Event<T> where AccountId = <T as system::Trait>::AccountId, Moment = <T as timestamp::Trait>::Moment, {
         //ERC734 events
         KeyAdded(AccountId, Key<AccountId>),
         KeyRemoved(AccountId, AccountId),
+        KeyPurposeAdded(AccountId, AccountId, u16),
+        KeyPurposeRemoved(AccountId, AccountId, u16),
+        PublicKeyAdded(AccountId, PublicKey),
+        PublicKeyRemoved(AccountId, Vec<u8>),
+        PublicKeyPurposeAdded(AccountId, Vec<u8>, u16),
+        PublicKeyPurposeRemoved(AccountId, Vec<u8>, u16),
         KeysRequiredChanged(u16, u16),
+        ExecutionRequested(u32, AccountId, AccountId),
+        Approved(u32, bool),
+        Executed(u32),
+        //execution id, error returned by the call
+        ExecutionFailed(u32, Vec<u8>),
 
         //ERC735 events
         ClaimAdded(AccountId, Vec<u8>),
         ClaimRemoved(AccountId, Vec<u8>),
+        ClaimChanged(AccountId, Vec<u8>),
+        ClaimExpired(AccountId, Vec<u8>),
+        ClaimRevoked(AccountId, Vec<u8>, u16, Moment),
+
+        //Recovery events
+        RecoveryConfigured(AccountId),
+        RecoveryRemoved(AccountId),
+        RecoveryInitiated(AccountId, AccountId),
+        RecoveryVouched(AccountId, AccountId),
+        RecoveryCancelled(AccountId),
+        RecoveryObjected(AccountId, AccountId),
+        RecoveryClosed(AccountId),
+        RecoveryClaimed(AccountId, AccountId),
+
+        //Topic registry events
+        TopicRegistered(u16, AccountId),
+        TopicUpdated(u16),
+        TrustedIssuerAdded(u16, AccountId),
+        TrustedIssuerRemoved(u16, AccountId),
     }
 }
 
 decl_module! {
     pub struct Module<T: Trait> for enum Call where origin: T::Origin {
         fn deposit_event<T>() = default;
+
+        fn on_initialize(n: T::BlockNumber) {
+            //Remove the claims scheduled for this block, bounded when they were scheduled
+            let due = <ExpiringClaims<T>>::take(n);
+            <ExpiringClaimsCount<T>>::remove(n);
+
+            for (forAccount, claimId) in due {
+                <ExpiryOf<T>>::remove(&claimId);
+
+                //Claim may have been migrated or changed without an expiry since it was scheduled
+                let claim = match Self::getClaimById(&claimId) {
+                    Some(claim) => claim,
+                    None => continue,
+                };
+                if claim.valid_until.as_ref().map_or(true, |until| *until > n) {
+                    continue;
+                }
+
+                //Revoked claims are kept so the revocation stays verifiable
+                if Self::isRevoked(claimId.clone()) {
+                    continue;
+                }
+
+                Self::deleteClaim(forAccount.clone(), claimId.clone(), &claim);
+                Self::deposit_event(RawEvent::ClaimExpired(forAccount, claimId));
+            }
+        }
         
         //ERC734(Setters + Side Effects)//
         fn addKey(origin, toAccount: T::AccountId, _key: T::AccountId, _purpose: u16, _keyType: u16) -> Result {
@@ -65,26 +343,27 @@ decl_module! {
 
             //Check if sender has management clearance
             if(sender.clone() != toAccount.clone()) {
-                ensure!(Self::keyHasPurpose(toAccount.clone(), sender, 1), "You are not authorized to do this.");
+                ensure!(Self::keyHasPurpose(toAccount.clone(), sender, MANAGEMENT_KEY), "You are not authorized to do this.");
+                ensure!(Self::getKeysRequired(toAccount.clone(), MANAGEMENT_KEY) <= 1, "Several management keys required - use execute");
             }
 
             //Check if key already exists
-            let keyTuple = (toAccount.clone(), _key.clone());
-            ensure!(!<Keys<T>>::exists(keyTuple), "Key already exists - change purpose?");
+            ensure!(Self::getKey(toAccount.clone(), _key.clone()).is_none(), "Key already exists - use addPurpose");
 
             //Add key to Keys
             let key = Key {
                 key: _key.clone(),
-                purpose: _purpose.clone(),
+                purposes: vec![_purpose],
                 keyType: _keyType,
             };
-            <Keys<T>>::insert((toAccount.clone(), _key.clone()), key.clone()); 
+            <KeyRecords<T>>::insert((toAccount.clone(), _key.clone()), key.clone()); 
             
             //Add Key to  KeysByPurpose
             let purposeTuple = (toAccount.clone(), _purpose.clone());
             let mut keyVector = Self::keysByPurpose(purposeTuple.clone());
             keyVector.push(_key.clone());
             <KeysByPurpose<T>>::insert(purposeTuple, keyVector);
+            <KeysOf<T>>::mutate(&toAccount, |keys| keys.push(_key.clone()));
 
             //Emit event
             Self::deposit_event(RawEvent::KeyAdded(toAccount, key));
@@ -92,45 +371,274 @@ decl_module! {
             Ok(())
         }
 
-        fn removeKey(origin, _key: T::AccountId, _purpose: u16) -> Result {
+        fn removeKey(origin, forAccount: T::AccountId, _key: T::AccountId) -> Result {
             let sender = ensure_signed(origin)?;
 
-            //Ensure key exists
-            let keyTuple = (sender.clone(), _key.clone());
-            ensure!(<Keys<T>>::exists(&keyTuple), "Key not found");
+            //Check if sender has management clearance
+            if(sender.clone() != forAccount.clone()) {
+                ensure!(Self::keyHasPurpose(forAccount.clone(), sender, MANAGEMENT_KEY), "You are not authorized to do this.");
+                ensure!(Self::getKeysRequired(forAccount.clone(), MANAGEMENT_KEY) <= 1, "Several management keys required - use execute");
+            }
 
-            //Remove key from Keys
-            <Keys<T>>::remove(keyTuple);
-            
-            //Remove Key from KeysByPurpose
-            let purposeTuple = (sender.clone(), _purpose.clone());
-            let keyToRemove = _key.clone();
-            let mut keysByPurposeVector = Self::keysByPurpose(&purposeTuple);
-            let index = keysByPurposeVector.iter().position(|item| *item == keyToRemove).unwrap();
-            keysByPurposeVector.remove(index);
-            <KeysByPurpose<T>>::insert(purposeTuple, keysByPurposeVector);
+            //Ensure key exists, its stored purposes are removed rather than the caller's
+            let key = Self::getKey(forAccount.clone(), _key.clone()).ok_or("Key not found")?;
+
+            //Ensure enough keys remain to meet each purpose's threshold
+            for purpose in key.purposes.iter() {
+                Self::ensureKeysRemain(forAccount.clone(), *purpose, &_key)?;
+            }
+
+            Self::deleteKey(forAccount.clone(), &key);
 
             //Emit event
-            Self::deposit_event(RawEvent::KeyRemoved(sender, _key));
+            Self::deposit_event(RawEvent::KeyRemoved(forAccount, _key));
             
             Ok(())
         }
 
+        fn addPurpose(origin, forAccount: T::AccountId, _key: T::AccountId, _purpose: u16) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            //Check if sender has management clearance
+            if(sender.clone() != forAccount.clone()) {
+                ensure!(Self::keyHasPurpose(forAccount.clone(), sender, MANAGEMENT_KEY), "You are not authorized to do this.");
+                ensure!(Self::getKeysRequired(forAccount.clone(), MANAGEMENT_KEY) <= 1, "Several management keys required - use execute");
+            }
+
+            //Ensure key exists and lacks the purpose
+            Self::migrateKey(&(forAccount.clone(), _key.clone()));
+            let mut key = Self::getKeyFor((forAccount.clone(), _key.clone())).ok_or("Key not found")?;
+            ensure!(!key.purposes.contains(&_purpose), "Key already has this purpose");
+
+            //Add purpose to key and KeysByPurpose
+            key.purposes.push(_purpose);
+            <KeyRecords<T>>::insert((forAccount.clone(), _key.clone()), key);
+            <KeysByPurpose<T>>::mutate((forAccount.clone(), _purpose), |keys| keys.push(_key.clone()));
+
+            //Emit event
+            Self::deposit_event(RawEvent::KeyPurposeAdded(forAccount, _key, _purpose));
+
+            Ok(())
+        }
+
+        fn removePurpose(origin, forAccount: T::AccountId, _key: T::AccountId, _purpose: u16) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            //Check if sender has management clearance
+            if(sender.clone() != forAccount.clone()) {
+                ensure!(Self::keyHasPurpose(forAccount.clone(), sender, MANAGEMENT_KEY), "You are not authorized to do this.");
+                ensure!(Self::getKeysRequired(forAccount.clone(), MANAGEMENT_KEY) <= 1, "Several management keys required - use execute");
+            }
+
+            //Ensure key has the purpose and enough keys remain to meet its threshold
+            Self::migrateKey(&(forAccount.clone(), _key.clone()));
+            let mut key = Self::getKeyFor((forAccount.clone(), _key.clone())).ok_or("Key not found")?;
+            let index = key.purposes.iter().position(|purpose| *purpose == _purpose).ok_or("Key does not have this purpose")?;
+            Self::ensureKeysRemain(forAccount.clone(), _purpose, &_key)?;
+
+            //A key without purposes is removed
+            if key.purposes.len() == 1 {
+                Self::deleteKey(forAccount.clone(), &key);
+                Self::deposit_event(RawEvent::KeyRemoved(forAccount, _key));
+                return Ok(());
+            }
+
+            //Remove purpose from key and KeysByPurpose
+            key.purposes.remove(index);
+            <KeyRecords<T>>::insert((forAccount.clone(), _key.clone()), key);
+            <KeysByPurpose<T>>::mutate((forAccount.clone(), _purpose), |keys| keys.retain(|item| *item != _key));
+
+            //Emit event
+            Self::deposit_event(RawEvent::KeyPurposeRemoved(forAccount, _key, _purpose));
+
+            Ok(())
+        }
+
         fn changeKeysRequired(origin, purpose: u16, number: u16) -> Result {
             let sender = ensure_signed(origin)?;
 
+            //Ensure threshold can be met by the keys registered for the purpose
+            ensure!(number > 0, "At least one key is required");
+            let purposeTuple = (sender, purpose);
+            if(number > 1) {
+                ensure!(Self::keysByPurpose(&purposeTuple).len() >= number as usize, "Not enough keys for this purpose");
+            }
+
+            //Store threshold
+            <KeysRequired<T>>::insert(purposeTuple, number);
+
+            //Emit event
+            Self::deposit_event(RawEvent::KeysRequiredChanged(purpose, number));
+
+            Ok(())
+        }
+
+        fn addPublicKey(origin, toAccount: T::AccountId, _key: Vec<u8>, _purpose: u16, _keyType: u16) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            //Check if sender has management clearance
+            if(sender.clone() != toAccount.clone()) {
+                ensure!(Self::keyHasPurpose(toAccount.clone(), sender, MANAGEMENT_KEY), "You are not authorized to do this.");
+                ensure!(Self::getKeysRequired(toAccount.clone(), MANAGEMENT_KEY) <= 1, "Several management keys required - use execute");
+            }
+
+            //Ensure key matches its type and does not exist yet
+            ensure!(Self::keyLength(_keyType) == Some(_key.len()), "Unsupported key type or wrong key length");
+            let keyTuple = (toAccount.clone(), _key.clone());
+            ensure!(Self::getPublicKey(&keyTuple).is_none(), "Key already exists - change purpose?");
+
+            //Add key to PublicKeys
+            let key = PublicKey {
+                purposes: vec![_purpose],
+                keyType: _keyType,
+                key: _key.clone(),
+            };
+            <PublicKeys<T>>::insert(keyTuple, key.clone());
+            <PublicKeysOf<T>>::mutate(&toAccount, |keys| keys.push(_key));
+
+            //Emit event
+            Self::deposit_event(RawEvent::PublicKeyAdded(toAccount, key));
+
+            Ok(())
+        }
+
+        fn removePublicKey(origin, forAccount: T::AccountId, _key: Vec<u8>) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            //Check if sender has management clearance
+            if(sender.clone() != forAccount.clone()) {
+                ensure!(Self::keyHasPurpose(forAccount.clone(), sender, MANAGEMENT_KEY), "You are not authorized to do this.");
+                ensure!(Self::getKeysRequired(forAccount.clone(), MANAGEMENT_KEY) <= 1, "Several management keys required - use execute");
+            }
+
+            //Ensure key exists
+            let keyTuple = (forAccount.clone(), _key.clone());
+            ensure!(Self::getPublicKey(&keyTuple).is_some(), "Key not found");
+
+            //Remove key from PublicKeys
+            <PublicKeys<T>>::remove(keyTuple);
+            <PublicKeysOf<T>>::mutate(&forAccount, |keys| keys.retain(|item| *item != _key));
+
+            //Emit event
+            Self::deposit_event(RawEvent::PublicKeyRemoved(forAccount, _key));
+
+            Ok(())
+        }
+
+        fn addPublicKeyPurpose(origin, forAccount: T::AccountId, _key: Vec<u8>, _purpose: u16) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            //Check if sender has management clearance
+            if(sender.clone() != forAccount.clone()) {
+                ensure!(Self::keyHasPurpose(forAccount.clone(), sender, MANAGEMENT_KEY), "You are not authorized to do this.");
+                ensure!(Self::getKeysRequired(forAccount.clone(), MANAGEMENT_KEY) <= 1, "Several management keys required - use execute");
+            }
+
+            //Ensure key exists and lacks the purpose
+            let keyTuple = (forAccount.clone(), _key.clone());
+            let mut key = Self::getPublicKey(&keyTuple).ok_or("Key not found")?;
+            ensure!(!key.purposes.contains(&_purpose), "Key already has this purpose");
+
+            //Add purpose to key
+            key.purposes.push(_purpose);
+            <PublicKeys<T>>::insert(keyTuple, key);
+
+            //Emit event
+            Self::deposit_event(RawEvent::PublicKeyPurposeAdded(forAccount, _key, _purpose));
+
+            Ok(())
+        }
+
+        fn removePublicKeyPurpose(origin, forAccount: T::AccountId, _key: Vec<u8>, _purpose: u16) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            //Check if sender has management clearance
+            if(sender.clone() != forAccount.clone()) {
+                ensure!(Self::keyHasPurpose(forAccount.clone(), sender, MANAGEMENT_KEY), "You are not authorized to do this.");
+                ensure!(Self::getKeysRequired(forAccount.clone(), MANAGEMENT_KEY) <= 1, "Several management keys required - use execute");
+            }
+
+            //Ensure key has the purpose
+            let keyTuple = (forAccount.clone(), _key.clone());
+            let mut key = Self::getPublicKey(&keyTuple).ok_or("Key not found")?;
+            let index = key.purposes.iter().position(|purpose| *purpose == _purpose).ok_or("Key does not have this purpose")?;
+
+            //A key without purposes is removed
+            if key.purposes.len() == 1 {
+                <PublicKeys<T>>::remove(keyTuple);
+                <PublicKeysOf<T>>::mutate(&forAccount, |keys| keys.retain(|item| *item != _key));
+                Self::deposit_event(RawEvent::PublicKeyRemoved(forAccount, _key));
+                return Ok(());
+            }
+
+            //Remove purpose from key
+            key.purposes.remove(index);
+            <PublicKeys<T>>::insert(keyTuple, key);
+
+            //Emit event
+            Self::deposit_event(RawEvent::PublicKeyPurposeRemoved(forAccount, _key, _purpose));
+
             Ok(())
         }
 
-        fn execute(origin, value: u32, data: Vec<u8>) -> Result {
+        fn execute(origin, identity: T::AccountId, call: Box<T::Proposal>) -> Result {
             let sender = ensure_signed(origin)?;
 
+            //Executions cannot be nested, an execution dispatched as the identity would approve itself
+            match call.is_aux_sub_type() {
+                Some(Call::execute(..)) | Some(Call::approve(..)) => return Err("Executions cannot be nested"),
+                _ => (),
+            }
+
+            //Check if sender has clearance for the call
+            let purpose = Self::purposeFor(&call);
+            let isKey = Self::canExecute(identity.clone(), sender.clone(), purpose);
+            ensure!(sender == identity || isKey, "You are not authorized to do this.");
+
+            //Record execution request, approved by the requester if it is a key of the identity
+            let id = Self::executionNonce();
+            let execution = Execution {
+                identity: identity.clone(),
+                purpose,
+                call: *call,
+                approvals: if isKey { vec![sender.clone()] } else { Vec::new() },
+            };
+            <ExecutionNonce<T>>::put(id.wrapping_add(1));
+
+            //Emit event
+            Self::deposit_event(RawEvent::ExecutionRequested(id, identity, sender));
+            if isKey {
+                Self::deposit_event(RawEvent::Approved(id, true));
+            }
+
+            Self::executeIfApproved(id, execution);
+
             Ok(())
         }
 
         fn approve(origin, id: u32, approval: bool) -> Result {
             let sender = ensure_signed(origin)?;
 
+            //Ensure execution is pending
+            let mut execution = Self::getExecution(id).ok_or("Execution not found")?;
+
+            //Check if sender has clearance for the call
+            ensure!(Self::canExecute(execution.identity.clone(), sender.clone(), execution.purpose), "You are not authorized to do this.");
+
+            //Add or withdraw approval
+            let index = execution.approvals.iter().position(|key| *key == sender);
+            match (approval, index) {
+                (true, None) => execution.approvals.push(sender),
+                (false, Some(index)) => { execution.approvals.remove(index); },
+                (true, Some(_)) => return Err("Already approved"),
+                (false, None) => return Err("Not approved yet"),
+            }
+
+            //Emit event
+            Self::deposit_event(RawEvent::Approved(id, approval));
+
+            Self::executeIfApproved(id, execution);
+
             Ok(())
         }
 
@@ -142,26 +650,17 @@ decl_module! {
                     issuer: T::AccountId, 
                     signature: Vec<u8>, 
                     data: Vec<u8>,
-                    uri: Vec<u8>) -> Result 
+                    uri: Vec<u8>,
+                    valid_from: Option<T::BlockNumber>,
+                    valid_until: Option<T::BlockNumber>) -> Result 
         {
             let sender = ensure_signed(origin)?;
-            
-            //Ensure sender is same as issuer, or has rights to add claim
-            if(sender.clone() != issuer.clone()) {
-                ensure!(Self::keyHasPurpose(issuer.clone(), sender.clone(), 3), "You are not authorized!");
-            }
-            
-            //Generate ClaimId
-            let mut to_account_as_bytes = toAccount.encode();
-            let mut issuer_as_bytes = issuer.encode();
-            let mut topic_as_bytes = topic.encode();
-            let claimId_bytes = [issuer_as_bytes, topic_as_bytes, to_account_as_bytes].concat();
-            let claimId = keccak_256(&claimId_bytes).to_vec();
-
-            //Check if claim already exists
-            ensure!(!<Claims<T>>::exists(&claimId), "Claim already exists!");
 
-            //Add claim to claims
+            //Ensure validity window is not empty or already over
+            Self::ensureValidityWindow(&valid_from, &valid_until)?;
+            let claimId = Self::claimIdFor(&issuer, topic, &toAccount);
+            let removeAt = Self::expiryBlock(&claimId, &valid_until)?;
+            
             let claim = Claim {
                 topic,
                 scheme,
@@ -169,14 +668,32 @@ decl_module! {
                 signature,
                 data,
                 uri,
+                valid_from,
+                valid_until,
             };
-            <Claims<T>>::insert(claimId.clone(), claim);
+
+            //Ensure sender is same as issuer, has rights to add claim or submits a claim signed by a claim key of the issuer
+            let signed = Self::ensureClaimAuthorized(&sender, &toAccount, &claim)?;
+
+            //Check if claim already exists
+            ensure!(Self::claimExists(claimId.clone()).is_err(), "Claim already exists!");
+
+            //A signed claim can only be submitted once
+            if signed {
+                Self::useClaimNonce(&claimId)?;
+            }
+
+            //Add claim to claims
+            <ClaimRecords<T>>::insert(claimId.clone(), claim);
+            Self::scheduleExpiry(toAccount.clone(), claimId.clone(), removeAt);
 
             //Add to claims by topic
             let claim_by_type_tuple = (toAccount.clone(), topic.clone());
             let mut claims_vector = Self::getClaimsByTopic(claim_by_type_tuple.clone());
             claims_vector.push(claimId.clone());
             <ClaimsByTopic<T>>::insert(claim_by_type_tuple, claims_vector);
+            <ClaimsOf<T>>::mutate(&toAccount, |claims| claims.push(claimId.clone()));
+            <ClaimsIssuedBy<T>>::mutate(&issuer, |claims| claims.push(claimId.clone()));
 
             //Emit event
             Self::deposit_event(RawEvent::ClaimAdded(toAccount, claimId));
@@ -185,91 +702,1138 @@ decl_module! {
         }
 
         fn changeClaim(origin, 
+                       forAccount: T::AccountId,
                        claimId: Vec<u8>,
                        topic: u16, 
                        scheme: u16, 
                        issuer: T::AccountId, 
                        signature: Vec<u8>, 
                        data: Vec<u8>,
-                       uri: Vec<u8>) -> Result 
+                       uri: Vec<u8>,
+                       valid_from: Option<T::BlockNumber>,
+                       valid_until: Option<T::BlockNumber>) -> Result 
         {
             let sender = ensure_signed(origin)?;
 
-            Ok(())
-        }
-
-        fn removeClaim(origin, forAccount: T::AccountId, claimId: Vec<u8>) -> Result {
-            let sender = ensure_signed(origin)?;
-            
             //Check if claim exists
-            ensure!(<Claims<T>>::exists(&claimId), "Claim not found!");
+            Self::claimExists(claimId.clone())?;
 
-            //Check if sender is issuer, has requisite authority or is owner
-            let claim_to_remove = Self::getClaimById(&claimId);
-            let issuer = claim_to_remove.issuer.clone();
-            if(sender.clone() != issuer.clone() && sender.clone() != forAccount) {
-                ensure!(Self::keyHasPurpose(issuer, sender, 3), "You are not authorized!");
-            }
+            //Ensure validity window is not empty or already over
+            Self::ensureValidityWindow(&valid_from, &valid_until)?;
+            let removeAt = Self::expiryBlock(&claimId, &valid_until)?;
 
-            //Remove from Claims
-            <Claims<T>>::remove(&claimId);
+            //Ensure topic and issuer are unchanged, they are part of the claimId
+            Self::migrateClaim(&claimId);
+            let previous_claim = Self::getClaimById(&claimId).ok_or("Claim not found!")?;
+            ensure!(previous_claim.topic == topic && previous_claim.issuer == issuer, "Topic and issuer cannot change - add a new claim");
 
-            //Remove from ClaimsByTopic
-            let claim_by_topic_tuple = (forAccount.clone(), claim_to_remove.topic.clone());
-            let claimId_to_remove = claimId.clone();
-            let mut claims_by_topic_vector = Self::getClaimsByTopic(&claim_by_topic_tuple);
-            let index = claims_by_topic_vector.iter().position(|item| *item == claimId_to_remove).unwrap();
-            claims_by_topic_vector.remove(index);
-            <ClaimsByTopic<T>>::insert(claim_by_topic_tuple, claims_by_topic_vector);
-            
-            //Emit event
-            Self::deposit_event(RawEvent::ClaimRemoved(forAccount, claimId));
+            let claim = Claim {
+                topic,
+                scheme,
+                issuer: issuer.clone(),
+                signature,
+                data,
+                uri,
+                valid_from,
+                valid_until,
+            };
 
-            Ok(())
-        }
-    }
-}
+            //Ensure sender is same as issuer, has rights to change claim or submits a claim signed by a claim key of the issuer
+            let signed = Self::ensureClaimAuthorized(&sender, &forAccount, &claim)?;
 
-impl<T: Trait> Module<T> {
-    //ERC734 Getters//
-    pub fn getKey(forAccount: T::AccountId, _key: T::AccountId) -> Key<T::AccountId> {
-        Self::getKey(forAccount, _key)
-    }
+            //Ensure the claim belongs to forAccount, which is part of the claimId
+            ensure!(Self::getClaimsByTopic((forAccount.clone(), topic)).contains(&claimId), "Claim not found!");
+            Self::indexClaim(&forAccount, &claimId, &issuer);
 
-    pub fn keyHasPurpose(forAccount: T::AccountId, _key: T::AccountId, _purpose: u16) -> bool {
-        //Check if key exists
-        let mut hasPurpose = false;
-        let keyTuple = (forAccount.clone(), _key.clone());
-        if (<Keys<T>>::exists(keyTuple.clone())) {
-            //Return if key has intended purpose or greater
-            hasPurpose = Self::getKeyFor(keyTuple).purpose <= _purpose;
-        };
+            //A signed claim can only be submitted once
+            if signed {
+                Self::useClaimNonce(&claimId)?;
+            }
 
-        hasPurpose
-    }
+            //Keep previous version, dropping the oldest beyond the limit
+            let mut history = Self::claimHistory(&claimId);
+            history.push(previous_claim);
+            if(history.len() > MAX_CLAIM_HISTORY) {
+                history.remove(0);
+            }
+            <ClaimHistory<T>>::insert(&claimId, history);
 
-    pub fn getKeysByPurpose(_forAccount: T::AccountId, _purpose: u16) -> Vec<T::AccountId> {
-        Self::keysByPurpose((_forAccount, _purpose))
-    }
+            //Update claim
+            <ClaimRecords<T>>::insert(&claimId, claim);
+            Self::scheduleExpiry(forAccount, claimId.clone(), removeAt);
 
-    // pub fn getKeysRequired(_purpose: u8) -> u8 {
+            //A claim issued again by the issuer itself is no longer revoked
+            if sender == issuer {
+                <Revocations<T>>::remove(&claimId);
+            }
 
-    // }
+            //Emit event
+            Self::deposit_event(RawEvent::ClaimChanged(issuer, claimId));
 
-    // //ERC735 Getters//
-    pub fn getClaim(claimId: Vec<u8>) -> Claim<T::AccountId> {
-        Self::getClaim(claimId)
+            Ok(())
+        }
+
+        fn revokeClaim(origin, claimId: Vec<u8>, reason: u16) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            //Check if claim exists and is not revoked yet
+            let claim = Self::getClaim(claimId.clone()).ok_or("Claim not found!")?;
+            ensure!(!Self::isRevoked(claimId.clone()), "Claim already revoked");
+
+            //Ensure sender is issuer, or has rights to revoke claim
+            let issuer = claim.issuer;
+            if(sender.clone() != issuer.clone()) {
+                ensure!(Self::keyHasPurpose(issuer.clone(), sender, CLAIM_SIGNER_KEY), "You are not authorized!");
+                ensure!(Self::getKeysRequired(issuer.clone(), CLAIM_SIGNER_KEY) <= 1, "Several claim keys required - use execute");
+            }
+
+            Self::revoke(issuer, claimId, reason);
+
+            Ok(())
+        }
+
+        fn revokeClaimsByTopic(origin, issuer: T::AccountId, topic: u16, reason: u16) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            //Ensure sender is issuer, or has rights to revoke claims
+            if(sender.clone() != issuer.clone()) {
+                ensure!(Self::keyHasPurpose(issuer.clone(), sender, CLAIM_SIGNER_KEY), "You are not authorized!");
+                ensure!(Self::getKeysRequired(issuer.clone(), CLAIM_SIGNER_KEY) <= 1, "Several claim keys required - use execute");
+            }
+
+            //Revoke claims on the topic the issuer still has outstanding, at most MAX_REVOCATIONS_PER_CALL
+            let claimIds = Self::claimsIssuedBy(issuer.clone())
+                .into_iter()
+                .filter(|claimId| !Self::isRevoked(claimId.clone()))
+                .filter(|claimId| Self::getClaim(claimId.clone()).map_or(false, |claim| claim.topic == topic))
+                .take(MAX_REVOCATIONS_PER_CALL)
+                .collect::<Vec<_>>();
+            ensure!(!claimIds.is_empty(), "No claims to revoke");
+
+            for claimId in claimIds {
+                Self::revoke(issuer.clone(), claimId, reason);
+            }
+
+            Ok(())
+        }
+
+        //Social Recovery//
+        fn setRecovery(origin, recoveryAccounts: Vec<T::AccountId>, threshold: u16, delay: T::BlockNumber) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            //Ensure no recovery is in progress
+            ensure!(Self::activeRecovery(&sender).is_none(), "Recovery in progress - cancel it first");
+
+            //Ensure recovery accounts are distinct and can meet the threshold
+            ensure!(recoveryAccounts.len() <= MAX_RECOVERY_ACCOUNTS, "Too many recovery accounts");
+            ensure!(threshold > 0 && recoveryAccounts.len() >= threshold as usize, "Threshold must be between one and the number of recovery accounts");
+            ensure!(delay >= <T::BlockNumber as As<u64>>::sa(MIN_RECOVERY_DELAY), "Recovery delay is too short");
+            for (index, account) in recoveryAccounts.iter().enumerate() {
+                ensure!(!recoveryAccounts[..index].contains(account), "Duplicate recovery account");
+                ensure!(*account != sender, "An identity cannot recover itself");
+            }
+
+            //Store configuration
+            let config = RecoveryConfig {
+                recoveryAccounts,
+                threshold,
+                delay,
+            };
+            <RecoveryConfigs<T>>::insert(&sender, config);
+
+            //Emit event
+            Self::deposit_event(RawEvent::RecoveryConfigured(sender));
+
+            Ok(())
+        }
+
+        fn removeRecovery(origin) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            //Ensure recovery is configured and not in progress
+            ensure!(Self::recoveryConfig(&sender).is_some(), "Recovery not configured");
+            ensure!(Self::activeRecovery(&sender).is_none(), "Recovery in progress - cancel it first");
+
+            <RecoveryConfigs<T>>::remove(&sender);
+
+            //Emit event
+            Self::deposit_event(RawEvent::RecoveryRemoved(sender));
+
+            Ok(())
+        }
+
+        fn initiateRecovery(origin, identity: T::AccountId, rescuer: T::AccountId) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            //Ensure sender is a recovery account and no recovery is in progress
+            let config = Self::recoveryConfig(&identity).ok_or("Recovery not configured")?;
+            ensure!(config.recoveryAccounts.contains(&sender), "You are not a recovery account for this identity");
+            ensure!(Self::activeRecovery(&identity).is_none(), "Recovery already in progress");
+
+            //Start recovery, vouched for by the initiator
+            let recovery = ActiveRecovery {
+                rescuer: rescuer.clone(),
+                started: <system::Module<T>>::block_number(),
+                vouchers: vec![sender.clone()],
+                objections: Vec::new(),
+            };
+            <ActiveRecoveries<T>>::insert(&identity, recovery);
+
+            //Emit event
+            Self::deposit_event(RawEvent::RecoveryInitiated(identity.clone(), rescuer));
+            Self::deposit_event(RawEvent::RecoveryVouched(identity, sender));
+
+            Ok(())
+        }
+
+        fn vouchRecovery(origin, identity: T::AccountId, rescuer: T::AccountId) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            //Ensure sender is a recovery account vouching for the recovery in progress
+            let config = Self::recoveryConfig(&identity).ok_or("Recovery not configured")?;
+            ensure!(config.recoveryAccounts.contains(&sender), "You are not a recovery account for this identity");
+            let mut recovery = Self::activeRecovery(&identity).ok_or("No recovery in progress")?;
+            ensure!(recovery.rescuer == rescuer, "Recovery in progress is for another account");
+            ensure!(!recovery.vouchers.contains(&sender), "Already vouched");
+
+            //Vouching withdraws an earlier objection
+            recovery.objections.retain(|account| *account != sender);
+            recovery.vouchers.push(sender.clone());
+            <ActiveRecoveries<T>>::insert(&identity, recovery);
+
+            //Emit event
+            Self::deposit_event(RawEvent::RecoveryVouched(identity, sender));
+
+            Ok(())
+        }
+
+        //Recovery accounts can close a recovery they do not support, so a single account
+        //cannot hold the identity's recovery with a rescuer nobody else vouches for
+        fn objectRecovery(origin, identity: T::AccountId) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            //Ensure sender is a recovery account objecting to the recovery in progress
+            let config = Self::recoveryConfig(&identity).ok_or("Recovery not configured")?;
+            ensure!(config.recoveryAccounts.contains(&sender), "You are not a recovery account for this identity");
+            let mut recovery = Self::activeRecovery(&identity).ok_or("No recovery in progress")?;
+            ensure!(!recovery.objections.contains(&sender), "Already objected");
+
+            //Objecting withdraws an earlier vouch
+            recovery.vouchers.retain(|account| *account != sender);
+            recovery.objections.push(sender.clone());
+
+            //Close once a threshold of recovery accounts objects, or earlier once too many object for the threshold to be reached
+            let accounts = config.recoveryAccounts.len();
+            let threshold = config.threshold as usize;
+            let needed = rstd::cmp::min(threshold, accounts.saturating_sub(threshold) + 1);
+            let objections = recovery.objections.iter().filter(|account| config.recoveryAccounts.contains(*account)).count();
+
+            //Emit event
+            Self::deposit_event(RawEvent::RecoveryObjected(identity.clone(), sender));
+
+            if objections >= needed {
+                <ActiveRecoveries<T>>::remove(&identity);
+                Self::deposit_event(RawEvent::RecoveryClosed(identity));
+            } else {
+                <ActiveRecoveries<T>>::insert(&identity, recovery);
+            }
+
+            Ok(())
+        }
+
+        //A single management key of the identity can cancel, even if several are required otherwise
+        fn cancelRecovery(origin, identity: T::AccountId) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            //Ensure sender controls the identity
+            ensure!(sender == identity || Self::canExecute(identity.clone(), sender, MANAGEMENT_KEY), "You are not authorized to do this.");
+            ensure!(<ActiveRecoveries<T>>::exists(&identity), "No recovery in progress");
+
+            <ActiveRecoveries<T>>::remove(&identity);
+
+            //Emit event
+            Self::deposit_event(RawEvent::RecoveryCancelled(identity));
+
+            Ok(())
+        }
+
+        fn claimRecovery(origin, identity: T::AccountId) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            //Ensure sender is the rescuer, enough recovery accounts vouched and the delay has passed
+            let config = Self::recoveryConfig(&identity).ok_or("Recovery not configured")?;
+            let recovery = Self::activeRecovery(&identity).ok_or("No recovery in progress")?;
+            ensure!(recovery.rescuer == sender, "You are not the rescuer of this recovery");
+            let vouchers = recovery.vouchers.iter().filter(|voucher| config.recoveryAccounts.contains(*voucher)).count();
+            ensure!(vouchers >= config.threshold as usize, "Not enough recovery accounts vouched");
+            let claimable = recovery.started.checked_add(&config.delay).ok_or("Overflow when checking delay")?;
+            ensure!(<system::Module<T>>::block_number() >= claimable, "Recovery delay has not passed");
+
+            <ActiveRecoveries<T>>::remove(&identity);
+            Self::recover(identity.clone(), sender.clone());
+
+            //Emit event
+            Self::deposit_event(RawEvent::RecoveryClaimed(identity, sender));
+
+            Ok(())
+        }
+
+        //Topic Registry//
+        //Topics are registered by governance, so the 16 bit topic space cannot be squatted
+        fn registerTopic(origin, topic: u16, owner: T::AccountId, description: Vec<u8>, schema: T::Hash) -> Result {
+            ensure_root(origin)?;
+
+            //Ensure topic is free and description is within the limit
+            ensure!(Self::getTopic(topic).is_none(), "Topic already registered");
+            ensure!(description.len() <= MAX_TOPIC_DESCRIPTION, "Topic description is too long");
+
+            //Owner can update the topic and its trusted issuers
+            let entry = Topic {
+                owner: owner.clone(),
+                description,
+                schema,
+            };
+            <Topics<T>>::insert(topic, entry);
+
+            //Emit event
+            Self::deposit_event(RawEvent::TopicRegistered(topic, owner));
+
+            Ok(())
+        }
+
+        fn updateTopic(origin, topic: u16, description: Vec<u8>, schema: T::Hash) -> Result {
+            let mut entry = Self::ensureTopicAdmin(origin, topic)?;
+
+            //Ensure description is within the limit
+            ensure!(description.len() <= MAX_TOPIC_DESCRIPTION, "Topic description is too long");
+
+            entry.description = description;
+            entry.schema = schema;
+            <Topics<T>>::insert(topic, entry);
+
+            //Emit event
+            Self::deposit_event(RawEvent::TopicUpdated(topic));
+
+            Ok(())
+        }
+
+        fn addTrustedIssuer(origin, topic: u16, issuer: T::AccountId) -> Result {
+            Self::ensureTopicAdmin(origin, topic)?;
+
+            //Ensure issuer is new and the list stays within the limit
+            let mut issuers = Self::trustedIssuers(topic);
+            ensure!(!issuers.contains(&issuer), "Issuer already trusted for this topic");
+            ensure!(issuers.len() < MAX_TRUSTED_ISSUERS, "Too many trusted issuers for this topic");
+
+            issuers.push(issuer.clone());
+            <TrustedIssuers<T>>::insert(topic, issuers);
+
+            //Emit event
+            Self::deposit_event(RawEvent::TrustedIssuerAdded(topic, issuer));
+
+            Ok(())
+        }
+
+        fn removeTrustedIssuer(origin, topic: u16, issuer: T::AccountId) -> Result {
+            Self::ensureTopicAdmin(origin, topic)?;
+
+            //Ensure issuer is trusted
+            let mut issuers = Self::trustedIssuers(topic);
+            let index = issuers.iter().position(|item| *item == issuer).ok_or("Issuer is not trusted for this topic")?;
+
+            issuers.remove(index);
+            <TrustedIssuers<T>>::insert(topic, issuers);
+
+            //Emit event
+            Self::deposit_event(RawEvent::TrustedIssuerRemoved(topic, issuer));
+
+            Ok(())
+        }
+
+        fn removeClaim(origin, forAccount: T::AccountId, claimId: Vec<u8>) -> Result {
+            let sender = ensure_signed(origin)?;
+            
+            //Check if claim exists
+            Self::claimExists(claimId.clone())?;
+
+            //Check if sender is issuer, has requisite authority or is owner
+            Self::migrateClaim(&claimId);
+            let claim_to_remove = Self::getClaimById(&claimId).ok_or("Claim not found!")?;
+            let issuer = claim_to_remove.issuer.clone();
+            if(sender.clone() != issuer.clone() && sender.clone() != forAccount) {
+                ensure!(Self::keyHasPurpose(issuer.clone(), sender, CLAIM_SIGNER_KEY), "You are not authorized!");
+                ensure!(Self::getKeysRequired(issuer, CLAIM_SIGNER_KEY) <= 1, "Several claim keys required - use execute");
+            }
+
+            //Ensure the claim belongs to forAccount
+            ensure!(Self::getClaimsByTopic((forAccount.clone(), claim_to_remove.topic)).contains(&claimId), "Claim not found!");
+
+            //Revoked claims are kept so the revocation stays verifiable
+            ensure!(!Self::isRevoked(claimId.clone()), "Claim is revoked - revoked claims cannot be removed");
+
+            Self::deleteClaim(forAccount.clone(), claimId.clone(), &claim_to_remove);
+            
+            //Emit event
+            Self::deposit_event(RawEvent::ClaimRemoved(forAccount, claimId));
+
+            Ok(())
+        }
+
+        //Migration//
+        //Moves keys stored before keys held several purposes to KeyRecords and adds them to KeysOf
+        //The (identity, key) pairs are listed by governance, pairs without a key are skipped
+        fn migrateKeys(origin, keys: Vec<(T::AccountId, T::AccountId)>) -> Result {
+            ensure_root(origin)?;
+
+            for keyTuple in keys {
+                Self::migrateKey(&keyTuple);
+                if Self::getKeyFor(&keyTuple).is_some() {
+                    Self::indexKey(&keyTuple.0, &keyTuple.1);
+                }
+            }
+
+            Ok(())
+        }
+
+        //Moves claims stored before validity windows were added to ClaimRecords and adds them to ClaimsOf and ClaimsIssuedBy
+        //The (subject, claimId) pairs are listed by governance, the subject must be the one the claimId was derived from
+        fn migrateClaims(origin, claims: Vec<(T::AccountId, Vec<u8>)>) -> Result {
+            ensure_root(origin)?;
+
+            for (forAccount, claimId) in claims {
+                let claim = match Self::getClaim(claimId.clone()) {
+                    Some(claim) => claim,
+                    None => continue,
+                };
+                ensure!(Self::claimIdFor(&claim.issuer, claim.topic, &forAccount) == claimId, "Claim was not made for this account");
+
+                Self::migrateClaim(&claimId);
+                Self::indexClaim(&forAccount, &claimId, &claim.issuer);
+            }
+
+            Ok(())
+        }
+    }
+}
+
+impl<T: Trait> Module<T> {
+    //ERC734 Getters//
+    pub fn getKey(forAccount: T::AccountId, _key: T::AccountId) -> Option<Key<T::AccountId>> {
+        let keyTuple = (forAccount, _key);
+        Self::getKeyFor(&keyTuple).or_else(|| Self::legacyKey(&keyTuple).map(Into::into))
+    }
+
+    pub fn getKeys(forAccount: T::AccountId) -> Vec<Key<T::AccountId>> {
+        Self::keysOf(&forAccount)
+            .into_iter()
+            .filter_map(|_key| Self::getKey(forAccount.clone(), _key))
+            .collect()
+    }
+
+    pub fn getPublicKeys(forAccount: T::AccountId) -> Vec<PublicKey> {
+        Self::publicKeysOf(&forAccount)
+            .into_iter()
+            .filter_map(|_key| Self::getPublicKey((forAccount.clone(), _key)))
+            .collect()
+    }
+
+    pub fn keyHasPurpose(forAccount: T::AccountId, _key: T::AccountId, _purpose: u16) -> bool {
+        //Return if key exists and has the intended purpose or is a management key
+        Self::getKey(forAccount, _key).map_or(false, |key| key.has_purpose(_purpose))
+    }
+
+    pub fn getKeysByPurpose(_forAccount: T::AccountId, _purpose: u16) -> Vec<T::AccountId> {
+        Self::keysByPurpose((_forAccount, _purpose))
+    }
+
+    pub fn getKeysRequired(forAccount: T::AccountId, _purpose: u16) -> u16 {
+        //A single key is enough unless a threshold was set
+        let required = Self::keysRequired((forAccount, _purpose));
+        if required == 0 { 1 } else { required }
+    }
+
+    //Ensures removing the key from a purpose leaves enough keys for the purpose's threshold
+    fn ensureKeysRemain(forAccount: T::AccountId, _purpose: u16, _key: &T::AccountId) -> Result {
+        let purposeTuple = (forAccount, _purpose);
+        let required = Self::keysRequired(&purposeTuple);
+        let remaining = Self::keysByPurpose(&purposeTuple).iter().filter(|item| *item != _key).count();
+        ensure!(required <= 1 || remaining >= required as usize, "Too few keys would remain - lower keys required first");
+        Ok(())
+    }
+
+    //Removes a key from every index
+    fn deleteKey(forAccount: T::AccountId, key: &Key<T::AccountId>) {
+        let keyTuple = (forAccount.clone(), key.key.clone());
+        <KeyRecords<T>>::remove(&keyTuple);
+        <Keys<T>>::remove(&keyTuple);
+        for purpose in key.purposes.iter() {
+            <KeysByPurpose<T>>::mutate((forAccount.clone(), *purpose), |keys| keys.retain(|item| *item != key.key));
+        }
+        <KeysOf<T>>::mutate(&forAccount, |keys| keys.retain(|item| *item != key.key));
+    }
+
+    //Replaces the management keys of an identity by the rescuer. As a management key the rescuer
+    //controls the remaining keys and, through execute, the claims of the identity
+    fn recover(identity: T::AccountId, rescuer: T::AccountId) {
+        for managementKey in Self::getKeysByPurpose(identity.clone(), MANAGEMENT_KEY) {
+            //Rescuer keeps its key and the purposes it already holds
+            if managementKey == rescuer {
+                continue;
+            }
+            if let Some(key) = Self::getKey(identity.clone(), managementKey) {
+                Self::deleteKey(identity.clone(), &key);
+                Self::deposit_event(RawEvent::KeyRemoved(identity.clone(), key.key));
+            }
+        }
+        <KeysRequired<T>>::remove((identity.clone(), MANAGEMENT_KEY));
+
+        //Rescuer may already hold other purposes
+        let keyTuple = (identity.clone(), rescuer.clone());
+        Self::migrateKey(&keyTuple);
+        let key = match Self::getKeyFor(&keyTuple) {
+            Some(ref key) if key.purposes.contains(&MANAGEMENT_KEY) => return,
+            Some(mut key) => {
+                key.purposes.push(MANAGEMENT_KEY);
+                key
+            },
+            None => {
+                <KeysOf<T>>::mutate(&identity, |keys| keys.push(rescuer.clone()));
+                Key {
+                    key: rescuer.clone(),
+                    purposes: vec![MANAGEMENT_KEY],
+                    keyType: SR25519_KEY,
+                }
+            },
+        };
+        <KeyRecords<T>>::insert(&keyTuple, key.clone());
+        <KeysByPurpose<T>>::mutate((identity.clone(), MANAGEMENT_KEY), |keys| keys.push(rescuer));
+
+        //Emit event
+        Self::deposit_event(RawEvent::KeyAdded(identity, key));
+    }
+
+    //Moves a key stored before keys held several purposes to KeyRecords and indexes it in KeysOf
+    fn migrateKey(keyTuple: &(T::AccountId, T::AccountId)) {
+        if let Some(key) = <Keys<T>>::take(keyTuple) {
+            <KeyRecords<T>>::insert(keyTuple, Key::from(key));
+            Self::indexKey(&keyTuple.0, &keyTuple.1);
+        }
+    }
+
+    //Adds a key to KeysOf, keys added before the index existed are missing from it
+    fn indexKey(forAccount: &T::AccountId, _key: &T::AccountId) {
+        <KeysOf<T>>::mutate(forAccount, |keys| {
+            if !keys.contains(_key) {
+                keys.push(_key.clone());
+            }
+        });
+    }
+
+    //ERC734 Execution//
+    //Only keys of the identity approve executions, the identity itself can request them but never approves
+    fn canExecute(identity: T::AccountId, _key: T::AccountId, _purpose: u16) -> bool {
+        Self::keyHasPurpose(identity, _key, _purpose)
+    }
+
+    //Key purpose needed to approve a call: managing keys and claims of the identity
+    //needs management or claim keys, anything else action keys
+    fn purposeFor(call: &T::Proposal) -> u16 {
+        match call.is_aux_sub_type() {
+            Some(Call::addKey(..)) | Some(Call::removeKey(..)) | Some(Call::changeKeysRequired(..)) |
+            Some(Call::addPurpose(..)) | Some(Call::removePurpose(..)) |
+            Some(Call::setRecovery(..)) | Some(Call::removeRecovery(..)) | Some(Call::cancelRecovery(..)) |
+            Some(Call::addPublicKey(..)) | Some(Call::removePublicKey(..)) |
+            Some(Call::addPublicKeyPurpose(..)) | Some(Call::removePublicKeyPurpose(..)) => MANAGEMENT_KEY,
+            Some(Call::addClaim(..)) | Some(Call::changeClaim(..)) | Some(Call::removeClaim(..)) |
+            Some(Call::revokeClaim(..)) | Some(Call::revokeClaimsByTopic(..)) => CLAIM_SIGNER_KEY,
+            _ => ACTION_KEY,
+        }
+    }
+
+    //Dispatches the call as the identity once enough keys approved, otherwise stores the request
+    fn executeIfApproved(id: u32, execution: Execution<T::AccountId, T::Proposal>) {
+        //Only count approvals from keys that still hold the purpose
+        let required = Self::getKeysRequired(execution.identity.clone(), execution.purpose) as usize;
+        let approvals = execution.approvals.iter()
+            .filter(|key| Self::canExecute(execution.identity.clone(), (*key).clone(), execution.purpose))
+            .count();
+        if approvals < required {
+            <Executions<T>>::insert(id, execution);
+            return;
+        }
+
+        <Executions<T>>::remove(id);
+        let origin = system::RawOrigin::Signed(execution.identity).into();
+        match execution.call.dispatch(origin) {
+            Ok(_) => Self::deposit_event(RawEvent::Executed(id)),
+            Err(e) => Self::deposit_event(RawEvent::ExecutionFailed(id, e.as_bytes().to_vec())),
+        }
+    }
+
+    //ERC735 Getters//
+    pub fn getClaim(claimId: Vec<u8>) -> Option<Claim<T::AccountId, T::BlockNumber>> {
+        Self::getClaimById(&claimId).or_else(|| Self::legacyClaim(&claimId).map(Into::into))
+    }
+
+    pub fn getClaimHistory(claimId: Vec<u8>) -> Vec<Claim<T::AccountId, T::BlockNumber>> {
+        Self::claimHistory(&claimId)
+    }
+
+    pub fn getClaims(forAccount: T::AccountId) -> Vec<(Vec<u8>, Claim<T::AccountId, T::BlockNumber>)> {
+        Self::claimsWithIds(Self::claimsOf(forAccount))
+    }
+
+    pub fn getClaimsIssuedBy(issuer: T::AccountId) -> Vec<(Vec<u8>, Claim<T::AccountId, T::BlockNumber>)> {
+        Self::claimsWithIds(Self::claimsIssuedBy(issuer))
     }
 
     pub fn getClaimIdsByTopic(forAccount: T::AccountId, _topic: u16) -> Vec<Vec<u8>> {
         Self::getClaimsByTopic((forAccount, _topic))
     }
 
+    pub fn isRevoked(claimId: Vec<u8>) -> bool {
+        <Revocations<T>>::exists(claimId)
+    }
+
+    fn claimsWithIds(claimIds: Vec<Vec<u8>>) -> Vec<(Vec<u8>, Claim<T::AccountId, T::BlockNumber>)> {
+        claimIds
+            .into_iter()
+            .filter_map(|claimId| Self::getClaim(claimId.clone()).map(|claim| (claimId, claim)))
+            .collect()
+    }
+
     pub fn claimExists(claimId: Vec<u8>) -> Result {
-        if <Claims<T>>::exists(claimId) {
+        if <ClaimRecords<T>>::exists(&claimId) || <Claims<T>>::exists(&claimId) {
             Ok(())
         } else {
             Err("Claim not found!")
         }
     }
+
+    //Key Verification//
+    //Length of keys of a supported type
+    pub fn keyLength(keyType: u16) -> Option<usize> {
+        match keyType {
+            ECDSA_KEY => Some(20),
+            SR25519_KEY | ED25519_KEY => Some(32),
+            _ => None,
+        }
+    }
+
+    //Checks a signature over a message by a key of the given type.
+    //ECDSA signatures are checked as Ethereum personal signatures over the keccak hash of the message,
+    //recovering the signer and comparing its address
+    pub fn verifySignature(keyType: u16, _key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+        if Self::keyLength(keyType) != Some(_key.len()) {
+            return false;
+        }
+
+        match keyType {
+            SR25519_KEY | ED25519_KEY => {
+                if signature.len() != 64 {
+                    return false;
+                }
+                let mut sig = [0u8; 64];
+                sig.copy_from_slice(signature);
+                if keyType == SR25519_KEY {
+                    runtime_io::sr25519_verify(&sig, message, _key)
+                } else {
+                    runtime_io::ed25519_verify(&sig, message, _key)
+                }
+            },
+            ECDSA_KEY => {
+                if signature.len() != 65 {
+                    return false;
+                }
+                let mut sig = [0u8; 65];
+                sig.copy_from_slice(signature);
+                let prefixed = [&b"\x19Ethereum Signed Message:\n32"[..], &keccak_256(message)[..]].concat();
+                match runtime_io::secp256k1_ecdsa_recover(&sig, &keccak_256(&prefixed)) {
+                    Ok(signer) => keccak_256(&signer[..])[12..] == *_key,
+                    Err(_) => false,
+                }
+            },
+            _ => false,
+        }
+    }
+
+    //Message an issuer signs for a claim: the subject and every field of the claim but the signature,
+    //with the claim's current nonce so a signed claim cannot be submitted twice
+    pub fn claimMessage(forAccount: &T::AccountId, claim: &Claim<T::AccountId, T::BlockNumber>, nonce: u64) -> Vec<u8> {
+        let hashed = [
+            forAccount.encode(),
+            claim.issuer.encode(),
+            claim.topic.encode(),
+            claim.scheme.encode(),
+            claim.data.encode(),
+            claim.uri.encode(),
+            claim.valid_from.encode(),
+            claim.valid_until.encode(),
+            nonce.encode(),
+        ].concat();
+        keccak_256(&hashed).to_vec()
+    }
+
+    //Checks that a claim was signed by the issuer account, one of its claim signer account keys
+    //or one of its claim signer public keys
+    pub fn claimSignatureIsValid(forAccount: &T::AccountId, claim: &Claim<T::AccountId, T::BlockNumber>) -> bool {
+        let issuer = &claim.issuer;
+        let signature = &claim.signature;
+        let claimId = Self::claimIdFor(issuer, claim.topic, forAccount);
+        let message = Self::claimMessage(forAccount, claim, Self::claimNonce(&claimId));
+
+        //Account keys
+        if let Some(sig) = T::Signature::decode(&mut &signature[..]) {
+            //KeysByPurpose also lists keys added before KeysOf existed
+            let mut signers = Self::keysByPurpose((issuer.clone(), CLAIM_SIGNER_KEY));
+            signers.extend(Self::keysByPurpose((issuer.clone(), MANAGEMENT_KEY)));
+            signers.push(issuer.clone());
+            if signers.iter().any(|signer| sig.verify(&message[..], signer)) {
+                return true;
+            }
+        }
+
+        //Public keys
+        Self::getPublicKeys(issuer.clone())
+            .into_iter()
+            .filter(|_key| _key.has_purpose(CLAIM_SIGNER_KEY))
+            .any(|_key| Self::verifySignature(_key.keyType, &_key.key, &message, signature))
+    }
+
+    //Ensures the sender is the issuer, one of its claim keys, or submits a claim signed for the issuer
+    //Returns whether the signature was used, so the caller can use up the claim's nonce
+    fn ensureClaimAuthorized(sender: &T::AccountId, forAccount: &T::AccountId, claim: &Claim<T::AccountId, T::BlockNumber>) -> rstd::result::Result<bool, &'static str> {
+        if *sender == claim.issuer {
+            return Ok(false);
+        }
+
+        let isClaimKey = Self::keyHasPurpose(claim.issuer.clone(), sender.clone(), CLAIM_SIGNER_KEY);
+        let signed = !isClaimKey && Self::claimSignatureIsValid(forAccount, claim);
+        ensure!(isClaimKey || signed, "You are not authorized!");
+        ensure!(Self::getKeysRequired(claim.issuer.clone(), CLAIM_SIGNER_KEY) <= 1, "Several claim keys required - use execute");
+
+        Ok(signed)
+    }
+
+    fn useClaimNonce(claimId: &Vec<u8>) -> Result {
+        let nonce = Self::claimNonce(claimId).checked_add(1).ok_or("Overflow")?;
+        <ClaimNonces<T>>::insert(claimId, nonce);
+        Ok(())
+    }
+
+    //Topic Registry//
+    pub fn isTrustedIssuer(topic: u16, issuer: &T::AccountId) -> bool {
+        Self::trustedIssuers(topic).contains(issuer)
+    }
+
+    //Ensures the origin is root or the topic owner and returns the topic
+    fn ensureTopicAdmin(origin: T::Origin, topic: u16) -> rstd::result::Result<Topic<T::AccountId, T::Hash>, &'static str> {
+        let entry = Self::getTopic(topic).ok_or("Topic not registered")?;
+        let origin: Option<system::RawOrigin<T::AccountId>> = origin.into();
+        match origin {
+            Some(system::RawOrigin::Root) => Ok(entry),
+            Some(system::RawOrigin::Signed(sender)) => {
+                ensure!(entry.owner == sender, "Only root or the topic owner can do this.");
+                Ok(entry)
+            },
+            _ => Err("bad origin: expected to be root or signed"),
+        }
+    }
+
+    //ERC735 Claim Lifecycle//
+    fn ensureValidityWindow(valid_from: &Option<T::BlockNumber>, valid_until: &Option<T::BlockNumber>) -> Result {
+        if let Some(until) = valid_until {
+            ensure!(*until > <system::Module<T>>::block_number(), "Claim would already be expired");
+            if let Some(from) = valid_from {
+                ensure!(from < until, "Claim must be valid from before it is valid until");
+            }
+        }
+
+        Ok(())
+    }
+
+    //Block at which an expiring claim is removed, the first block from its expiry with room left
+    //The block the claim is already scheduled at has room for it, its entry there is moved by `scheduleExpiry`
+    fn expiryBlock(claimId: &Vec<u8>, valid_until: &Option<T::BlockNumber>) -> rstd::result::Result<Option<T::BlockNumber>, &'static str> {
+        let scheduled = Self::expiryBlockOf(claimId);
+        let until = match valid_until {
+            Some(until) => *until,
+            None => return Ok(None),
+        };
+
+        for delay in 0..MAX_EXPIRY_DELAY {
+            let at = until.checked_add(&<T::BlockNumber as As<u64>>::sa(delay)).ok_or("Overflow")?;
+            if (Self::expiringClaimsCount(at) as usize) < MAX_EXPIRED_CLAIMS_PER_BLOCK || scheduled == Some(at) {
+                return Ok(Some(at));
+            }
+        }
+
+        Err("Too many claims expire around this block - choose another expiry")
+    }
+
+    //Schedules a claim's removal at a block returned by `expiryBlock`, replacing any earlier schedule
+    fn scheduleExpiry(forAccount: T::AccountId, claimId: Vec<u8>, removeAt: Option<T::BlockNumber>) {
+        Self::unscheduleExpiry(&claimId);
+        if let Some(at) = removeAt {
+            <ExpiringClaims<T>>::mutate(&at, |claims| claims.push((forAccount, claimId.clone())));
+            <ExpiringClaimsCount<T>>::mutate(&at, |count| *count += 1);
+            <ExpiryOf<T>>::insert(&claimId, at);
+        }
+    }
+
+    //Drops a claim's scheduled removal, freeing its room in that block
+    fn unscheduleExpiry(claimId: &Vec<u8>) {
+        if let Some(at) = <ExpiryOf<T>>::take(claimId) {
+            <ExpiringClaims<T>>::mutate(&at, |claims| claims.retain(|(_, item)| item != claimId));
+            <ExpiringClaimsCount<T>>::mutate(&at, |count| *count = count.saturating_sub(1));
+        }
+    }
+
+    //Records a revocation, the claim itself stays visible and no longer expires
+    fn revoke(issuer: T::AccountId, claimId: Vec<u8>, reason: u16) {
+        Self::unscheduleExpiry(&claimId);
+        let revoked_on = <timestamp::Module<T>>::now();
+        <Revocations<T>>::insert(&claimId, Revocation { reason, revoked_on: revoked_on.clone() });
+        Self::deposit_event(RawEvent::ClaimRevoked(issuer, claimId, reason, revoked_on));
+    }
+
+    //Removes a claim and its history from every index
+    fn deleteClaim(forAccount: T::AccountId, claimId: Vec<u8>, claim: &Claim<T::AccountId, T::BlockNumber>) {
+        <ClaimRecords<T>>::remove(&claimId);
+        <ClaimHistory<T>>::remove(&claimId);
+        Self::unscheduleExpiry(&claimId);
+        <ClaimsByTopic<T>>::mutate((forAccount.clone(), claim.topic), |claims| claims.retain(|item| *item != claimId));
+        <ClaimsOf<T>>::mutate(&forAccount, |claims| claims.retain(|item| *item != claimId));
+        <ClaimsIssuedBy<T>>::mutate(&claim.issuer, |claims| claims.retain(|item| *item != claimId));
+    }
+
+    //Adds a claim to ClaimsOf and ClaimsIssuedBy, claims added before the indexes existed are missing from them
+    fn indexClaim(forAccount: &T::AccountId, claimId: &Vec<u8>, issuer: &T::AccountId) {
+        <ClaimsOf<T>>::mutate(forAccount, |claims| {
+            if !claims.contains(claimId) {
+                claims.push(claimId.clone());
+            }
+        });
+        <ClaimsIssuedBy<T>>::mutate(issuer, |claims| {
+            if !claims.contains(claimId) {
+                claims.push(claimId.clone());
+            }
+        });
+    }
+
+    //Claim id of a subject's claim by an issuer on a topic
+    pub fn claimIdFor(issuer: &T::AccountId, topic: u16, forAccount: &T::AccountId) -> Vec<u8> {
+        let claimId_bytes = [issuer.encode(), topic.encode(), forAccount.encode()].concat();
+        keccak_256(&claimId_bytes).to_vec()
+    }
+
+    //Moves a claim stored before validity windows were added to ClaimRecords as a claim that never expires
+    //The subject is not stored in the claim, so only the issuer's index can be filled here
+    fn migrateClaim(claimId: &Vec<u8>) {
+        if let Some(claim) = <Claims<T>>::take(claimId) {
+            <ClaimsIssuedBy<T>>::mutate(&claim.issuer, |claims| {
+                if !claims.contains(claimId) {
+                    claims.push(claimId.clone());
+                }
+            });
+            <ClaimRecords<T>>::insert(claimId, Claim::from(claim));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use runtime_io::with_externalities;
+    use primitives::{H256, Blake2Hasher};
+    use support::{impl_outer_origin, assert_ok, assert_noop};
+    use runtime_primitives::{
+        BuildStorage,
+        traits::{BlakeTwo256, IdentityLookup, Lazy, OnInitialize},
+        testing::{Digest, DigestItem, Header},
+    };
+
+    impl_outer_origin! {
+        pub enum Origin for Test {}
+    }
+
+    #[derive(Clone, Eq, PartialEq, Debug)]
+    pub struct Test;
+
+    impl system::Trait for Test {
+        type Origin = Origin;
+        type Index = u64;
+        type BlockNumber = u64;
+        type Hash = H256;
+        type Hashing = BlakeTwo256;
+        type Digest = Digest;
+        type AccountId = u64;
+        type Lookup = IdentityLookup<u64>;
+        type Header = Header;
+        type Event = ();
+        type Log = DigestItem;
+    }
+
+    impl timestamp::Trait for Test {
+        type Moment = u64;
+        type OnTimestampSet = ();
+    }
+
+    impl balances::Trait for Test {
+        type Balance = u64;
+        type OnFreeBalanceZero = ();
+        type OnNewAccount = ();
+        type Event = ();
+        type TransactionPayment = ();
+        type DustRemoval = ();
+        type TransferPayment = ();
+    }
+
+    // a signature is valid for the account it names
+    #[derive(Encode, Decode, Clone, PartialEq, Eq, Debug)]
+    pub struct TestSignature(u64);
+
+    impl Verify for TestSignature {
+        type Signer = u64;
+        fn verify<L: Lazy<[u8]>>(&self, _msg: L, signer: &u64) -> bool {
+            self.0 == *signer
+        }
+    }
+
+    impl token::Trait for Test {
+        type Event = ();
+        type TokenBalance = u64;
+        type Signature = TestSignature;
+    }
+
+    impl Trait for Test {
+        type Event = ();
+        type Proposal = Call<Test>;
+    }
+
+    type Id = Module<Test>;
+
+    const IDENTITY: u64 = 1;
+    const KEY_A: u64 = 2;
+    const KEY_B: u64 = 3;
+    const NEW_KEY: u64 = 4;
+    const RECOVERY_A: u64 = 5;
+    const RECOVERY_B: u64 = 6;
+    const RECOVERY_C: u64 = 7;
+    const RESCUER: u64 = 8;
+    const ISSUER: u64 = 9;
+
+    const TOPIC: u16 = 7;
+
+    fn new_test_ext() -> runtime_io::TestExternalities<Blake2Hasher> {
+        let t = system::GenesisConfig::<Test>::default().build_storage().unwrap().0;
+        let mut ext: runtime_io::TestExternalities<Blake2Hasher> = t.into();
+        with_externalities(&mut ext, || {
+            system::Module::<Test>::set_block_number(1);
+        });
+        ext
+    }
+
+    // KEY_A and KEY_B are management keys of IDENTITY, both needed to manage it
+    fn setup_two_of_two() {
+        assert_ok!(Id::addKey(Origin::signed(IDENTITY), IDENTITY, KEY_A, MANAGEMENT_KEY, SR25519_KEY));
+        assert_ok!(Id::addKey(Origin::signed(IDENTITY), IDENTITY, KEY_B, MANAGEMENT_KEY, SR25519_KEY));
+        assert_ok!(Id::changeKeysRequired(Origin::signed(IDENTITY), MANAGEMENT_KEY, 2));
+    }
+
+    fn add_new_key() -> Box<Call<Test>> {
+        Box::new(Call::addKey(IDENTITY, NEW_KEY, ACTION_KEY, SR25519_KEY))
+    }
+
+    // RECOVERY_A, RECOVERY_B and RECOVERY_C can recover IDENTITY, two of them together
+    fn setup_recovery() {
+        let accounts = vec![RECOVERY_A, RECOVERY_B, RECOVERY_C];
+        assert_ok!(Id::setRecovery(Origin::signed(IDENTITY), accounts, 2, MIN_RECOVERY_DELAY));
+    }
+
+    fn add_claim(valid_until: Option<u64>) -> Vec<u8> {
+        assert_ok!(Id::addClaim(Origin::signed(ISSUER), IDENTITY, TOPIC, 1, ISSUER, vec![], b"data".to_vec(), vec![], None, valid_until));
+        Id::claimIdFor(&ISSUER, TOPIC, &IDENTITY)
+    }
+
+    #[test]
+    fn execution_waits_for_enough_approvals() {
+        with_externalities(&mut new_test_ext(), || {
+            setup_two_of_two();
+
+            assert_ok!(Id::execute(Origin::signed(KEY_A), IDENTITY, add_new_key()));
+            assert!(Id::getExecution(0).is_some());
+            assert!(Id::getKey(IDENTITY, NEW_KEY).is_none());
+
+            assert_ok!(Id::approve(Origin::signed(KEY_B), 0, true));
+            assert!(Id::getExecution(0).is_none());
+            assert!(Id::keyHasPurpose(IDENTITY, NEW_KEY, ACTION_KEY));
+        });
+    }
+
+    #[test]
+    fn approvals_of_removed_keys_are_not_counted() {
+        with_externalities(&mut new_test_ext(), || {
+            setup_two_of_two();
+            assert_ok!(Id::addKey(Origin::signed(IDENTITY), IDENTITY, RESCUER, MANAGEMENT_KEY, SR25519_KEY));
+
+            assert_ok!(Id::execute(Origin::signed(KEY_A), IDENTITY, add_new_key()));
+
+            // KEY_A loses its management purpose, its approval no longer counts
+            assert_ok!(Id::removePurpose(Origin::signed(IDENTITY), IDENTITY, KEY_A, MANAGEMENT_KEY));
+            assert_ok!(Id::approve(Origin::signed(KEY_B), 0, true));
+            assert!(Id::getExecution(0).is_some());
+            assert!(Id::getKey(IDENTITY, NEW_KEY).is_none());
+
+            assert_ok!(Id::approve(Origin::signed(RESCUER), 0, true));
+            assert!(Id::keyHasPurpose(IDENTITY, NEW_KEY, ACTION_KEY));
+        });
+    }
+
+    #[test]
+    fn approve_rejects_accounts_without_the_purpose() {
+        with_externalities(&mut new_test_ext(), || {
+            setup_two_of_two();
+            assert_ok!(Id::execute(Origin::signed(KEY_A), IDENTITY, add_new_key()));
+
+            assert_noop!(Id::approve(Origin::signed(NEW_KEY), 0, true), "You are not authorized to do this.");
+            assert_noop!(Id::approve(Origin::signed(KEY_A), 0, true), "Already approved");
+        });
+    }
+
+    #[test]
+    fn executions_cannot_be_nested() {
+        with_externalities(&mut new_test_ext(), || {
+            setup_two_of_two();
+
+            assert_noop!(
+                Id::execute(Origin::signed(KEY_A), IDENTITY, Box::new(Call::approve(0, true))),
+                "Executions cannot be nested"
+            );
+            assert_noop!(
+                Id::execute(Origin::signed(KEY_A), IDENTITY, Box::new(Call::execute(IDENTITY, add_new_key()))),
+                "Executions cannot be nested"
+            );
+        });
+    }
+
+    #[test]
+    fn recovery_needs_threshold_and_delay() {
+        with_externalities(&mut new_test_ext(), || {
+            setup_recovery();
+            assert_noop!(
+                Id::setRecovery(Origin::signed(IDENTITY), vec![RECOVERY_A], 1, MIN_RECOVERY_DELAY - 1),
+                "Recovery delay is too short"
+            );
+
+            assert_ok!(Id::initiateRecovery(Origin::signed(RECOVERY_A), IDENTITY, RESCUER));
+            assert_noop!(Id::claimRecovery(Origin::signed(RESCUER), IDENTITY), "Not enough recovery accounts vouched");
+
+            assert_ok!(Id::vouchRecovery(Origin::signed(RECOVERY_B), IDENTITY, RESCUER));
+            assert_noop!(Id::claimRecovery(Origin::signed(RESCUER), IDENTITY), "Recovery delay has not passed");
+
+            system::Module::<Test>::set_block_number(1 + MIN_RECOVERY_DELAY);
+            assert_ok!(Id::claimRecovery(Origin::signed(RESCUER), IDENTITY));
+            assert!(Id::keyHasPurpose(IDENTITY, RESCUER, MANAGEMENT_KEY));
+            assert!(Id::activeRecovery(IDENTITY).is_none());
+        });
+    }
+
+    #[test]
+    fn recovery_replaces_management_keys_but_keeps_the_rescuers_purposes() {
+        with_externalities(&mut new_test_ext(), || {
+            assert_ok!(Id::addKey(Origin::signed(IDENTITY), IDENTITY, KEY_A, MANAGEMENT_KEY, SR25519_KEY));
+            assert_ok!(Id::addKey(Origin::signed(IDENTITY), IDENTITY, RESCUER, CLAIM_SIGNER_KEY, SR25519_KEY));
+            setup_recovery();
+
+            assert_ok!(Id::initiateRecovery(Origin::signed(RECOVERY_A), IDENTITY, RESCUER));
+            assert_ok!(Id::vouchRecovery(Origin::signed(RECOVERY_B), IDENTITY, RESCUER));
+            system::Module::<Test>::set_block_number(1 + MIN_RECOVERY_DELAY);
+            assert_ok!(Id::claimRecovery(Origin::signed(RESCUER), IDENTITY));
+
+            assert!(Id::getKey(IDENTITY, KEY_A).is_none());
+            let rescuer = Id::getKey(IDENTITY, RESCUER).unwrap();
+            assert_eq!(rescuer.purposes, vec![CLAIM_SIGNER_KEY, MANAGEMENT_KEY]);
+            assert_eq!(Id::getKeysByPurpose(IDENTITY, MANAGEMENT_KEY), vec![RESCUER]);
+            assert_eq!(Id::getKeysByPurpose(IDENTITY, CLAIM_SIGNER_KEY), vec![RESCUER]);
+        });
+    }
+
+    #[test]
+    fn objections_close_a_recovery_that_cannot_reach_its_threshold() {
+        with_externalities(&mut new_test_ext(), || {
+            setup_recovery();
+            assert_ok!(Id::initiateRecovery(Origin::signed(RECOVERY_A), IDENTITY, RESCUER));
+
+            // with two of three needed, a second objection leaves too few accounts to vouch
+            assert_ok!(Id::objectRecovery(Origin::signed(RECOVERY_B), IDENTITY));
+            assert!(Id::activeRecovery(IDENTITY).is_some());
+            assert_ok!(Id::objectRecovery(Origin::signed(RECOVERY_C), IDENTITY));
+            assert!(Id::activeRecovery(IDENTITY).is_none());
+
+            assert_noop!(Id::objectRecovery(Origin::signed(RESCUER), IDENTITY), "You are not a recovery account for this identity");
+        });
+    }
+
+    #[test]
+    fn expired_claims_are_removed_at_their_block() {
+        with_externalities(&mut new_test_ext(), || {
+            let claimId = add_claim(Some(10));
+            assert_eq!(Id::expiringClaimsCount(10), 1);
+
+            <Id as OnInitialize<u64>>::on_initialize(9);
+            assert!(Id::getClaim(claimId.clone()).is_some());
+
+            <Id as OnInitialize<u64>>::on_initialize(10);
+            assert!(Id::getClaim(claimId.clone()).is_none());
+            assert!(Id::getClaims(IDENTITY).is_empty());
+            assert!(Id::getClaimIdsByTopic(IDENTITY, TOPIC).is_empty());
+            assert!(Id::expiryBlockOf(claimId).is_none());
+        });
+    }
+
+    #[test]
+    fn changing_a_claims_expiry_moves_its_schedule() {
+        with_externalities(&mut new_test_ext(), || {
+            let claimId = add_claim(Some(10));
+            assert_ok!(Id::changeClaim(Origin::signed(ISSUER), IDENTITY, claimId.clone(), TOPIC, 1, ISSUER, vec![], b"new".to_vec(), vec![], None, Some(20)));
+
+            assert_eq!(Id::expiringClaimsCount(10), 0);
+            assert!(Id::expiringClaims(10).is_empty());
+            assert_eq!(Id::expiringClaimsCount(20), 1);
+
+            <Id as OnInitialize<u64>>::on_initialize(10);
+            assert!(Id::getClaim(claimId.clone()).is_some());
+            <Id as OnInitialize<u64>>::on_initialize(20);
+            assert!(Id::getClaim(claimId).is_none());
+        });
+    }
+
+    #[test]
+    fn removing_a_claim_frees_its_expiry_slot() {
+        with_externalities(&mut new_test_ext(), || {
+            let claimId = add_claim(Some(10));
+            assert_ok!(Id::removeClaim(Origin::signed(IDENTITY), IDENTITY, claimId.clone()));
+
+            assert_eq!(Id::expiringClaimsCount(10), 0);
+            assert!(Id::expiryBlockOf(claimId).is_none());
+        });
+    }
+
+    #[test]
+    fn revoked_claims_are_kept() {
+        with_externalities(&mut new_test_ext(), || {
+            let claimId = add_claim(Some(10));
+            assert_ok!(Id::revokeClaim(Origin::signed(ISSUER), claimId.clone(), 1));
+
+            assert_noop!(
+                Id::removeClaim(Origin::signed(IDENTITY), IDENTITY, claimId.clone()),
+                "Claim is revoked - revoked claims cannot be removed"
+            );
+            <Id as OnInitialize<u64>>::on_initialize(10);
+            assert!(Id::getClaim(claimId.clone()).is_some());
+            assert!(Id::isRevoked(claimId));
+        });
+    }
 }
@@ -18,7 +18,7 @@ use runtime_primitives::{
 };
 use client::{
 	block_builder::api::{CheckInherentsResult, InherentData, self as block_builder_api},
-	runtime_api, impl_runtime_apis
+	runtime_api, impl_runtime_apis, decl_runtime_apis
 };
 use version::RuntimeVersion;
 #[cfg(feature = "std")]
@@ -61,6 +61,12 @@ mod token;
 /// ERC734/735 identity system
 mod id;
 
+/// Content-addressed document store
+mod preimage;
+
+/// Transaction fees payable in tokens
+mod fees;
+
 /// Business Logic
 mod bnft;
 
@@ -96,13 +102,25 @@ pub mod opaque {
 	pub type SessionKey = AuthorityId;
 }
 
+decl_runtime_apis! {
+	/// Access to the documents bnft classes refer to.
+	pub trait DocumentApi {
+		/// Get the document stored under the given hash.
+		fn document(hash: Hash) -> Option<Vec<u8>>;
+		/// Get the ricardian contract of a bnft class.
+		fn ricardian_contract(class_index: u32) -> Option<Vec<u8>>;
+		/// Get the description of a bnft class.
+		fn description(class_index: u32) -> Option<Vec<u8>>;
+	}
+}
+
 /// This runtime version.
 pub const VERSION: RuntimeVersion = RuntimeVersion {
 	spec_name: create_runtime_str!("bnft"),
 	impl_name: create_runtime_str!("bnft"),
 	authoring_version: 5,
-	spec_version: 6,
-	impl_version: 6,
+	spec_version: 7,
+	impl_version: 7,
 	apis: RUNTIME_API_VERSIONS,
 };
 
@@ -196,14 +214,25 @@ impl sudo::Trait for Runtime {
 impl token::Trait for Runtime {
     type Event = Event;
     type TokenBalance = u128;
+    type Signature = AccountSignature;
 }
 
 impl id::Trait for Runtime {
     type Event = Event;
+    type Proposal = Call;
+}
+
+impl fees::Trait for Runtime {
+    type Event = Event;
+}
+
+impl preimage::Trait for Runtime {
+    type Event = Event;
 }
 
 impl bnft::Trait for Runtime {
     type Event = Event;
+    type NativeCurrency = Balances;
 }
 
 construct_runtime!(
@@ -223,6 +252,8 @@ construct_runtime!(
 	    Bnft: bnft::{Module, Call, Storage, Event<T>, Config<T>},
             Token: token::{Module, Call, Storage, Event<T>, Config<T>},
             Id: id::{Module, Call, Storage, Event<T>},
+            Preimage: preimage::{Module, Call, Storage, Event<T>, Config<T>},
+            Fees: fees::{Module, Call, Storage, Event<T>},
 	}
 );
 
@@ -241,7 +272,8 @@ pub type UncheckedExtrinsic = generic::UncheckedMortalCompactExtrinsic<Address,
 /// Extrinsic type that has already been checked.
 pub type CheckedExtrinsic = generic::CheckedExtrinsic<AccountId, Nonce, Call>;
 /// Executive: handles dispatch to the various modules.
-pub type Executive = executive::Executive<Runtime, Block, Context, Balances, AllModules>;
+/// Fees are paid through the fees module, which falls back to Balances.
+pub type Executive = executive::Executive<Runtime, Block, Context, Fees, AllModules>;
 
 // Implement our runtime API endpoints. This is just a bunch of proxying.
 impl_runtime_apis! {
@@ -314,4 +346,18 @@ impl_runtime_apis! {
 			Consensus::authorities()
 		}
 	}
+
+	impl self::DocumentApi<Block> for Runtime {
+		fn document(hash: Hash) -> Option<Vec<u8>> {
+			Preimage::preimage(hash)
+		}
+
+		fn ricardian_contract(class_index: u32) -> Option<Vec<u8>> {
+			Bnft::ricardian_contract_of(class_index)
+		}
+
+		fn description(class_index: u32) -> Option<Vec<u8>> {
+			Bnft::description_of(class_index)
+		}
+	}
 }
@@ -0,0 +1,121 @@
+// runtime module storing documents (descriptions, ricardian contracts) by their hash
+// anyone can note a document by reserving a deposit proportional to its size,
+// several accounts can hold a deposit for the same document
+// a class can only reference documents its creator holds a deposit for,
+// and that deposit can no longer be removed while the class refers to it
+
+use rstd::prelude::*;
+use support::{StorageValue, StorageMap, ensure, dispatch::Result, decl_module, decl_storage, decl_event};
+use support::traits::ReservableCurrency;
+use runtime_primitives::traits::{Hash, As, CheckedMul};
+use system::ensure_signed;
+
+pub trait Trait: balances::Trait {
+    type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+}
+
+decl_storage! {
+    trait Store for Module<T: Trait> as Preimage {
+        //Limits and deposits, set in the genesis config
+        MaxPreimageSize get(max_preimage_size) config(): u32;
+        PreimageByteDeposit get(preimage_byte_deposit) config(): T::Balance;
+
+        //Document storage
+        Preimages get(preimage): map T::Hash => Option<Vec<u8>>;
+        PreimageDeposits get(deposit_of): map (T::Hash, T::AccountId) => Option<T::Balance>;
+        //Number of accounts holding a deposit for a document, it is removed with the last deposit
+        Depositors get(depositor_count): map T::Hash => u32;
+
+        //Number of bnft classes referring to a document through an account's deposit
+        References get(references): map (T::Hash, T::AccountId) => u32;
+    }
+}
+
+decl_event! {
+    pub enum Event<T> where
+        AccountId = <T as system::Trait>::AccountId,
+        Hash = <T as system::Trait>::Hash,
+        Balance = <T as balances::Trait>::Balance,
+    {
+        PreimageNoted(Hash, AccountId, Balance),
+        PreimageUnnoted(Hash, AccountId, Balance),
+    }
+}
+
+decl_module! {
+    pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+        fn deposit_event<T>() = default;
+
+        fn note_preimage(origin, document: Vec<u8>) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            //Ensure document is within the size limit
+            ensure!(document.len() <= Self::max_preimage_size() as usize, "Document is too large");
+
+            //Ensure sender holds no deposit for the document yet
+            let hash = T::Hashing::hash(&document);
+            let depositKey = (hash.clone(), sender.clone());
+            ensure!(!<PreimageDeposits<T>>::exists(&depositKey), "Document already noted");
+
+            //Reserve deposit
+            let deposit = Self::preimage_byte_deposit()
+                .checked_mul(&<T::Balance as As<u64>>::sa(document.len() as u64))
+                .ok_or("Overflow when calculating deposit")?;
+            <balances::Module<T>>::reserve(&sender, deposit)?;
+
+            //Save document, unless another account noted it already
+            if !<Preimages<T>>::exists(&hash) {
+                <Preimages<T>>::insert(&hash, document);
+            }
+            <PreimageDeposits<T>>::insert(&depositKey, deposit);
+            <Depositors<T>>::mutate(&hash, |count| *count = count.saturating_add(1));
+
+            //Emit event
+            Self::deposit_event(RawEvent::PreimageNoted(hash, sender, deposit));
+
+            Ok(())
+        }
+
+        fn unnote_preimage(origin, hash: T::Hash) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            //Ensure sender holds a deposit for the document
+            let depositKey = (hash.clone(), sender.clone());
+            let deposit = Self::deposit_of(&depositKey).ok_or("Document not noted by this account")?;
+
+            //Ensure no bnft class refers to it through this deposit
+            ensure!(Self::references(&depositKey) == 0, "Document is referenced by a bnft class");
+
+            //Return deposit, the document is removed with the last one
+            <PreimageDeposits<T>>::remove(&depositKey);
+            let depositors = Self::depositor_count(&hash).saturating_sub(1);
+            if depositors == 0 {
+                <Preimages<T>>::remove(&hash);
+                <Depositors<T>>::remove(&hash);
+            } else {
+                <Depositors<T>>::insert(&hash, depositors);
+            }
+            <balances::Module<T>>::unreserve(&sender, deposit);
+
+            //Emit event
+            Self::deposit_event(RawEvent::PreimageUnnoted(hash, sender, deposit));
+
+            Ok(())
+        }
+    }
+}
+
+impl<T: Trait> Module<T> {
+    // checks that `who` holds a deposit for a document
+    pub fn is_noted_by(hash: &T::Hash, who: &T::AccountId) -> bool {
+        <PreimageDeposits<T>>::exists((hash.clone(), who.clone()))
+    }
+
+    // marks `who`'s deposit for a document as referenced so it can no longer be unnoted
+    // an account can only pin its own deposit, other depositors are not affected
+    pub fn add_reference(hash: T::Hash, who: &T::AccountId) -> Result {
+        ensure!(Self::is_noted_by(&hash, who), "Document must be noted by the class creator");
+        <References<T>>::mutate((hash, who.clone()), |count| *count = count.saturating_add(1));
+        Ok(())
+    }
+}
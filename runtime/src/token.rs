@@ -1,19 +1,175 @@
-/// runtime module implementing the ERC20 token interface
+/// runtime module implementing the ERC20 token interface for multiple assets
 /// with added lock and unlock functions for staking in TCR runtime
 /// implements a custom type `TokenBalance` for representing account balance
 /// `TokenBalance` type is exactly the same as the `Balance` type in `balances` SRML module
+/// every asset is identified by an `AssetId`, asset 0 is the default token created in genesis
 
 use rstd::prelude::*;
-use parity_codec::Codec;
+use parity_codec::{Codec, Encode, Decode};
 use support::{dispatch::Result, StorageMap, Parameter, StorageValue, decl_storage, decl_module, decl_event, ensure};
-use system::{self, ensure_signed};
-use runtime_primitives::traits::{CheckedSub, CheckedAdd, Member, SimpleArithmetic, As};
+use system::{self, ensure_signed, ensure_root};
+use runtime_primitives::traits::{CheckedSub, CheckedAdd, CheckedMul, Member, SimpleArithmetic, As, Verify, Zero};
+
+// identifier of an asset managed by this module
+pub type AssetId = u32;
+
+// the asset created in the genesis config and handed out by init()
+pub const DEFAULT_ASSET: AssetId = 0;
+
+// tag at the start of every permit signature, so it cannot be mistaken for another signed message
+pub const PERMIT_TAG: &[u8] = b"token:permit";
+
+// most vesting schedules an account can hold at once
+pub const MAX_VESTING_SCHEDULES: usize = 20;
+
+// most vesting schedules an account can hold for vested_transfer to add another
+// the rest are kept for deposits unlocked into vesting, so cheap transfers cannot crowd them out
+pub const MAX_VESTED_TRANSFERS: usize = 10;
+
+// latest cliff vested_transfer accepts, in blocks from now, about a year at ten second blocks
+// so every schedule an account receives matures and is dropped eventually
+pub const MAX_VESTING_CLIFF: u64 = 3_153_600;
 
 // trait for this module
 // contains type definitions
 pub trait Trait: system::Trait {
     type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
     type TokenBalance: Parameter + Member + SimpleArithmetic + Codec + Default + Copy + As<usize> + As<u64>;
+    // signature made off-chain by an account, used by permit
+    type Signature: Parameter + Verify<Signer = Self::AccountId>;
+}
+
+// why tokens are locked
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq)]
+pub enum LockReason {
+    // bounties deposited by the funder of a bnft class
+    Funding,
+    // stake deposited by an agent issuing a bnft
+    Stake,
+    // deposit backing a challenge
+    Challenge,
+}
+
+// identifies a locked deposit, one variant per purpose
+// so deposits for different purposes can never share a key
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+pub enum LockId<AccountId> {
+    // bounties for a bnft class: funder, class index
+    Funding(AccountId, u32),
+    // stake for an issued bnft: uri, class index
+    Stake(AccountId, u32),
+    // deposit backing a challenge: challenger, challenge index
+    Challenge(AccountId, u32),
+}
+
+impl<AccountId: Clone> LockId<AccountId> {
+    pub fn reason(&self) -> LockReason {
+        match self {
+            LockId::Funding(..) => LockReason::Funding,
+            LockId::Stake(..) => LockReason::Stake,
+            LockId::Challenge(..) => LockReason::Challenge,
+        }
+    }
+
+    // the untyped key this deposit was stored under before lock ids existed
+    fn legacy_key(&self) -> Option<(AccountId, u32)> {
+        match self {
+            LockId::Funding(funder, class_index) => Some((funder.clone(), *class_index)),
+            LockId::Stake(uri, class_index) => Some((uri.clone(), *class_index)),
+            LockId::Challenge(..) => None,
+        }
+    }
+}
+
+// errors returned by lock, unlock and slash
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum LockError {
+    // the account has never held this asset
+    NoBalance,
+    // the account's free balance is lower than the amount to lock
+    InsufficientBalance,
+    // the deposit is lower than the amount to release
+    InsufficientDeposit,
+    // the deposit was locked by another account
+    WrongOwner,
+    // a balance, deposit or supply would overflow
+    Overflow,
+    // the account already holds the most vesting schedules allowed
+    TooManySchedules,
+}
+
+impl From<LockError> for &'static str {
+    fn from(err: LockError) -> &'static str {
+        match err {
+            LockError::NoBalance => "Account does not own this token",
+            LockError::InsufficientBalance => "Not enough balance.",
+            LockError::InsufficientDeposit => "Not enough locked deposit.",
+            LockError::WrongOwner => "Deposit is locked by another account",
+            LockError::Overflow => "overflow in calculating deposit",
+            LockError::TooManySchedules => "Account has too many vesting schedules.",
+        }
+    }
+}
+
+// tokens granted to an account that mature block by block
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Encode, Decode, Default, Clone, PartialEq, Eq)]
+pub struct VestingSchedule<Balance, BlockNumber> {
+    asset_id: AssetId,
+    // total amount granted
+    total: Balance,
+    // amount already released by vest
+    released: Balance,
+    // amount maturing each block after start
+    per_block: Balance,
+    start: BlockNumber,
+    // nothing can be released before this block
+    cliff: BlockNumber,
+}
+
+impl<Balance: SimpleArithmetic + Copy, BlockNumber: SimpleArithmetic + Copy> VestingSchedule<Balance, BlockNumber> {
+    // amount matured at block `now`, never more than the total
+    fn vested_at(&self, now: BlockNumber) -> Balance {
+        if now < self.start || now < self.cliff {
+            return Zero::zero();
+        }
+        let elapsed = <Balance as As<u64>>::sa((now - self.start).as_());
+        match self.per_block.checked_mul(&elapsed) {
+            Some(vested) if vested < self.total => vested,
+            _ => self.total,
+        }
+    }
+}
+
+// descriptive information about an asset
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Encode, Decode, Default, Clone, PartialEq, Eq)]
+pub struct AssetMetadata {
+    name: Vec<u8>,
+    symbol: Vec<u8>,
+    decimals: u8,
+}
+
+// storage layout of the single asset token module, before assets existed
+// the items share the `Token` prefix and names with the current storage, only their keys differ
+// read once to move existing values to the default asset, see `migrate_balances` and `migrate_locks`
+mod legacy {
+    use super::Trait;
+    use support::{StorageMap, StorageValue, decl_storage};
+
+    pub struct Module<T: Trait>(rstd::marker::PhantomData<T>);
+
+    decl_storage! {
+      trait Store for Module<T: Trait> as Token {
+        pub TotalSupply: Option<T::TokenBalance>;
+        pub BalanceOf: map T::AccountId => Option<T::TokenBalance>;
+        pub Allowance: map (T::AccountId, T::AccountId) => Option<T::TokenBalance>;
+        pub LockedDeposits: map (T::AccountId, u32) => Option<T::TokenBalance>;
+      }
+    }
 }
 
 // public interface for this runtime module
@@ -22,48 +178,341 @@ decl_module! {
       // initialize the default event for this module
       fn deposit_event<T>() = default;
 
+      // move the total supply stored before assets existed to the default asset, once
+      fn on_initialize(_n: T::BlockNumber) {
+          if let Some(supply) = <legacy::TotalSupply<T>>::take() {
+              <TotalSupply<T>>::insert(DEFAULT_ASSET, supply);
+          }
+      }
+
+      // move balances stored before assets existed to the default asset
+      // the accounts are listed by governance, accounts without an old balance are skipped
+      pub fn migrate_balances(origin, accounts: Vec<T::AccountId>) -> Result {
+          ensure_root(origin)?;
+
+          for who in accounts {
+              let balance = match <legacy::BalanceOf<T>>::get(&who) {
+                  Some(balance) => balance,
+                  None => continue,
+              };
+
+              // tokens received since the upgrade are kept
+              let key = (DEFAULT_ASSET, who.clone());
+              let updated_balance = Self::balance_of(&key).checked_add(&balance).ok_or("overflow in calculating balance")?;
+
+              <legacy::BalanceOf<T>>::remove(&who);
+              Self::set_balance(key, updated_balance);
+          }
+
+          Ok(())
+      }
+
+      // move allowances stored before assets existed to the default asset
+      // the (owner, spender) pairs are listed by governance, pairs without an old allowance are skipped
+      pub fn migrate_allowances(origin, pairs: Vec<(T::AccountId, T::AccountId)>) -> Result {
+          ensure_root(origin)?;
+
+          for (owner, spender) in pairs {
+              let allowance = match <legacy::Allowance<T>>::take((owner.clone(), spender.clone())) {
+                  Some(allowance) => allowance,
+                  None => continue,
+              };
+
+              // an allowance set since the upgrade is newer and wins
+              let key = (DEFAULT_ASSET, owner, spender);
+              if !<Allowance<T>>::exists(&key) {
+                  <Allowance<T>>::insert(key, allowance);
+              }
+          }
+
+          Ok(())
+      }
+
+      // move deposits locked before lock ids existed to the default asset
+      // the old (account, index) key did not record whether a deposit was bounties or stake,
+      // so governance lists each deposit split into its lock ids with their owners
+      // a deposit is only moved if its parts add up to it exactly
+      pub fn migrate_locks(origin, locks: Vec<((T::AccountId, u32), Vec<(LockId<T::AccountId>, T::AccountId, T::TokenBalance)>)>) -> Result {
+          ensure_root(origin)?;
+
+          for (legacyKey, parts) in locks {
+              let deposit = match <legacy::LockedDeposits<T>>::get(&legacyKey) {
+                  Some(deposit) => deposit,
+                  None => continue,
+              };
+
+              // check the whole split before moving any of it
+              let mut total: T::TokenBalance = Zero::zero();
+              for (index, (lock_id, owner, value)) in parts.iter().enumerate() {
+                  ensure!(lock_id.legacy_key().as_ref() == Some(&legacyKey), "Lock id does not belong to this deposit.");
+                  ensure!(parts[..index].iter().all(|(other, _, _)| other != lock_id), "Lock id is listed twice.");
+
+                  // deposits locked since the upgrade are kept, they must have the same owner
+                  let lockKey = (DEFAULT_ASSET, lock_id.clone());
+                  if let Some(lock_owner) = Self::lock_owner(&lockKey) {
+                      ensure!(lock_owner == *owner, "Deposit is locked by another account");
+                  }
+                  Self::locked_deposits(&lockKey).checked_add(value).ok_or("overflow in calculating deposit")?;
+                  Self::total_locked((DEFAULT_ASSET, owner.clone())).checked_add(&deposit).ok_or("overflow in calculating deposit")?;
+                  total = total.checked_add(value).ok_or("overflow in calculating deposit")?;
+              }
+              ensure!(total == deposit, "Lock parts do not add up to the deposit.");
+
+              <legacy::LockedDeposits<T>>::remove(&legacyKey);
+              for (lock_id, owner, value) in parts {
+                  Self::add_to_lock(DEFAULT_ASSET, owner.clone(), value, lock_id.clone());
+                  Self::deposit_event(RawEvent::Locked(DEFAULT_ASSET, owner, lock_id.clone(), lock_id.reason(), value));
+              }
+          }
+
+          Ok(())
+      }
+
+      // create a new asset owned by the sender
+      // the initial supply is credited to the sender
+      pub fn create_asset(origin, name: Vec<u8>, symbol: Vec<u8>, decimals: u8, #[compact] initial_supply: T::TokenBalance) -> Result {
+          let sender = ensure_signed(origin)?;
+
+          // asset 0 is reserved for the default token
+          let asset_id = Self::next_asset_id().max(DEFAULT_ASSET + 1);
+          let next_asset_id = asset_id.checked_add(1).ok_or("overflow in calculating asset id")?;
+
+          <TotalSupply<T>>::insert(asset_id, initial_supply);
+          Self::set_balance((asset_id, sender.clone()), initial_supply);
+          <AssetOwner<T>>::insert(asset_id, sender.clone());
+          <Metadata<T>>::insert(asset_id, AssetMetadata { name, symbol, decimals });
+          <NextAssetId<T>>::put(next_asset_id);
+
+          Self::deposit_event(RawEvent::AssetCreated(asset_id, sender, initial_supply));
+          Ok(())
+      }
+
+      // issue new tokens of an asset to an account
+      // only root, the asset owner or one of its minters can mint
+      pub fn mint(origin, asset_id: AssetId, to: T::AccountId, #[compact] value: T::TokenBalance) -> Result {
+          Self::ensure_minter(origin, asset_id)?;
+
+          let updated_supply = Self::total_supply(asset_id).checked_add(&value).ok_or("overflow in calculating supply")?;
+          if let Some(cap) = Self::supply_cap(asset_id) {
+              ensure!(updated_supply <= cap, "Minting would exceed the supply cap.");
+          }
+          let updated_balance = Self::balance_of((asset_id, to.clone())).checked_add(&value).ok_or("overflow in calculating balance")?;
+
+          <TotalSupply<T>>::insert(asset_id, updated_supply);
+          Self::set_balance((asset_id, to.clone()), updated_balance);
+
+          Self::deposit_event(RawEvent::Mint(asset_id, to, value));
+          Ok(())
+      }
+
+      // destroy tokens of an asset held by an account
+      // only the asset owner or one of its minters can burn, from their own balance
+      // or from an account that approved them for at least `value`
+      pub fn burn(origin, asset_id: AssetId, from: T::AccountId, #[compact] value: T::TokenBalance) -> Result {
+          let sender = ensure_signed(origin)?;
+          ensure!(Self::can_mint(asset_id, &sender), "Only the asset owner or a minter can do this.");
+
+          // burning someone else's tokens spends their allowance
+          let updated_allowance = if sender != from {
+              let allowance = Self::allowance((asset_id, from.clone(), sender.clone()));
+              Some(allowance.checked_sub(&value).ok_or("Not enough allowance.")?)
+          } else {
+              None
+          };
+
+          let updated_balance = Self::balance_of((asset_id, from.clone())).checked_sub(&value).ok_or("Not enough balance.")?;
+          let updated_supply = Self::total_supply(asset_id).checked_sub(&value).ok_or("overflow in calculating supply")?;
+
+          if let Some(updated_allowance) = updated_allowance {
+              Self::_set_allowance(asset_id, from.clone(), sender, updated_allowance);
+          }
+          <TotalSupply<T>>::insert(asset_id, updated_supply);
+          Self::set_balance((asset_id, from.clone()), updated_balance);
+
+          Self::deposit_event(RawEvent::Burn(asset_id, from, value));
+          Ok(())
+      }
+
+      // allow an account to mint and burn an asset
+      // only root or the asset owner can add minters
+      pub fn add_minter(origin, asset_id: AssetId, minter: T::AccountId) -> Result {
+          Self::ensure_asset_admin(origin, asset_id)?;
+
+          <Minters<T>>::insert((asset_id, minter.clone()), true);
+
+          Self::deposit_event(RawEvent::MinterAdded(asset_id, minter));
+          Ok(())
+      }
+
+      // revoke an account's right to mint and burn an asset
+      // only root or the asset owner can remove minters
+      pub fn remove_minter(origin, asset_id: AssetId, minter: T::AccountId) -> Result {
+          Self::ensure_asset_admin(origin, asset_id)?;
+          ensure!(Self::is_minter((asset_id, minter.clone())), "Account is not a minter.");
+
+          <Minters<T>>::remove((asset_id, minter.clone()));
+
+          Self::deposit_event(RawEvent::MinterRemoved(asset_id, minter));
+          Ok(())
+      }
+
+      // limit the total supply of an asset, `None` removes the limit
+      // only root or the asset owner can change the cap
+      pub fn set_supply_cap(origin, asset_id: AssetId, cap: Option<T::TokenBalance>) -> Result {
+          Self::ensure_asset_admin(origin, asset_id)?;
+
+          match cap {
+              Some(cap) => {
+                  ensure!(cap >= Self::total_supply(asset_id), "Cap is below the current supply.");
+                  <SupplyCap<T>>::insert(asset_id, cap);
+              },
+              None => <SupplyCap<T>>::remove(asset_id),
+          }
+
+          Self::deposit_event(RawEvent::SupplyCapChanged(asset_id, cap));
+          Ok(())
+      }
+
+      // update the name, symbol and decimals of an asset
+      // only the owner of the asset can change its metadata
+      pub fn set_metadata(origin, asset_id: AssetId, name: Vec<u8>, symbol: Vec<u8>, decimals: u8) -> Result {
+          let sender = ensure_signed(origin)?;
+          ensure!(Self::asset_owner(asset_id) == Some(sender), "Only the asset owner can change metadata");
+
+          let metadata = AssetMetadata { name, symbol, decimals };
+          <Metadata<T>>::insert(asset_id, metadata.clone());
+
+          Self::deposit_event(RawEvent::MetadataSet(asset_id, metadata));
+          Ok(())
+      }
+
       // transfer tokens from one account to another
-      pub fn transfer(origin, to: T::AccountId, #[compact] value: T::TokenBalance) -> Result {
+      pub fn transfer(origin, asset_id: AssetId, to: T::AccountId, #[compact] value: T::TokenBalance) -> Result {
           let sender = ensure_signed(origin)?;
-          Self::_transfer(sender, to, value)
+          Self::_transfer(asset_id, sender, to, value)
       }
 
       // approve token transfer from one account to another
       // once this is done, then transfer_from can be called with corresponding values
-      pub fn approve(origin, spender: T::AccountId, #[compact] value: T::TokenBalance) -> Result {
+      pub fn approve(origin, asset_id: AssetId, spender: T::AccountId, #[compact] value: T::TokenBalance) -> Result {
           let sender = ensure_signed(origin)?;
           // make sure the approver/owner owns this token
-          ensure!(<BalanceOf<T>>::exists(&sender), "Account does not own this token");
+          ensure!(<BalanceOf<T>>::exists((asset_id, sender.clone())), "Account does not own this token");
 
           // get the current value of the allowance for this sender and spender combination
           // if doesnt exist then default 0 will be returned
-          let allowance = Self::allowance((sender.clone(), spender.clone()));
-          
+          let allowance = Self::allowance((asset_id, sender.clone(), spender.clone()));
+
           // add the value to the current allowance
           // using checked_add (safe math) to avoid overflow
           let updated_allowance = allowance.checked_add(&value).ok_or("overflow in calculating allowance")?;
 
           // insert the new allowance value of this sender and spender combination
-          <Allowance<T>>::insert((sender.clone(), spender.clone()), updated_allowance);
-          
-          // raise the approval event
-          Self::deposit_event(RawEvent::Approval(sender, spender, value));
+          <Allowance<T>>::insert((asset_id, sender.clone(), spender.clone()), updated_allowance);
+
+          // raise the approval event with the new allowance
+          Self::deposit_event(RawEvent::Approval(asset_id, sender, spender, updated_allowance));
+          Ok(())
+      }
+
+      // reduce the allowance of a spender
+      pub fn decrease_allowance(origin, asset_id: AssetId, spender: T::AccountId, #[compact] value: T::TokenBalance) -> Result {
+          let sender = ensure_signed(origin)?;
+          let allowance = Self::allowance((asset_id, sender.clone(), spender.clone()));
+
+          // using checked_sub (safe math) to avoid underflow
+          let updated_allowance = allowance.checked_sub(&value).ok_or("Allowance would fall below zero.")?;
+          <Allowance<T>>::insert((asset_id, sender.clone(), spender.clone()), updated_allowance);
+
+          // raise the approval event with the new allowance
+          Self::deposit_event(RawEvent::Approval(asset_id, sender, spender, updated_allowance));
+          Ok(())
+      }
+
+      // set the allowance of a spender to an exact value
+      // only succeeds if the current allowance is still `expected`
+      // so a spender cannot use the old and the new allowance by front-running the change
+      pub fn set_allowance(origin, asset_id: AssetId, spender: T::AccountId, #[compact] expected: T::TokenBalance, #[compact] value: T::TokenBalance) -> Result {
+          let sender = ensure_signed(origin)?;
+          ensure!(Self::allowance((asset_id, sender.clone(), spender.clone())) == expected, "Allowance has changed.");
+
+          Self::_set_allowance(asset_id, sender, spender, value);
+          Ok(())
+      }
+
+      // set an allowance with the owner's off-chain signature, anyone can submit it
+      // the owner signs (PERMIT_TAG, genesis hash, asset_id, owner, spender, value, nonce, deadline)
+      // the genesis hash binds the permit to this chain, like the EIP-2612 domain separator
+      pub fn permit(origin,
+                    asset_id: AssetId,
+                    owner: T::AccountId,
+                    spender: T::AccountId,
+                    #[compact] value: T::TokenBalance,
+                    deadline: T::BlockNumber,
+                    signature: T::Signature) -> Result {
+          let _ = ensure_signed(origin)?;
+
+          // make sure the permit has not expired
+          ensure!(<system::Module<T>>::block_number() <= deadline, "Permit has expired.");
+
+          // make sure the owner signed this permit with their current nonce
+          let nonce = Self::permit_nonce(&owner);
+          let message = Self::permit_message(asset_id, &owner, &spender, value, nonce, deadline);
+          ensure!(signature.verify(&message[..], &owner), "Invalid permit signature.");
+
+          // a permit can only be used once
+          let updated_nonce = nonce.checked_add(1).ok_or("overflow in calculating nonce")?;
+          <PermitNonces<T>>::insert(&owner, updated_nonce);
+
+          Self::_set_allowance(asset_id, owner, spender, value);
+          Ok(())
+      }
+
+      // release all matured tokens from the sender's vesting schedules
+      pub fn vest(origin) -> Result {
+          let sender = ensure_signed(origin)?;
+          Self::_vest(sender)
+      }
+
+      // transfer tokens to an account as a vesting schedule starting now
+      // `per_block` tokens mature each block, none can be released before `cliff`
+      pub fn vested_transfer(origin, asset_id: AssetId, to: T::AccountId, #[compact] value: T::TokenBalance, #[compact] per_block: T::TokenBalance, cliff: T::BlockNumber) -> Result {
+          let sender = ensure_signed(origin)?;
+          ensure!(!per_block.is_zero(), "Vesting schedule must release tokens each block.");
+          ensure!(Self::vesting_schedules(&to).len() < MAX_VESTED_TRANSFERS, "Account has too many vesting schedules.");
+          let latest_cliff = <system::Module<T>>::block_number().checked_add(&<T::BlockNumber as As<u64>>::sa(MAX_VESTING_CLIFF)).ok_or("overflow in calculating cliff")?;
+          ensure!(cliff <= latest_cliff, "Vesting cliff is too far in the future.");
+
+          let sender_balance = Self::balance_of((asset_id, sender.clone()));
+          let updated_from_balance = sender_balance.checked_sub(&value).ok_or("Not enough balance.")?;
+
+          // deduct the grant from the sender's balance
+          Self::set_balance((asset_id, sender), updated_from_balance);
+
+          Self::add_vesting_schedule(asset_id, to, value, per_block, cliff);
           Ok(())
       }
 
-      // if approved, transfer from an account to another account without needing owner's signature
-      pub fn transfer_from(_origin, from: T::AccountId, to: T::AccountId, #[compact] value: T::TokenBalance) -> Result {
-          ensure!(<Allowance<T>>::exists((from.clone(), to.clone())), "Allowance does not exist.");
-          let allowance = Self::allowance((from.clone(), to.clone()));
+      // if approved, the spender (sender) can transfer from the owner's account to any account
+      // without needing owner's signature
+      pub fn transfer_from(origin, asset_id: AssetId, from: T::AccountId, to: T::AccountId, #[compact] value: T::TokenBalance) -> Result {
+          let spender = ensure_signed(origin)?;
+          ensure!(<Allowance<T>>::exists((asset_id, from.clone(), spender.clone())), "Allowance does not exist.");
+          let allowance = Self::allowance((asset_id, from.clone(), spender.clone()));
           ensure!(allowance >= value, "Not enough allowance.");
 
           // using checked_sub (safe math) to avoid overflow
           let updated_allowance = allowance.checked_sub(&value).ok_or("overflow in calculating allowance")?;
-          // insert the new allownace value of this sender and spender combination
-          <Allowance<T>>::insert((from.clone(), to.clone()), updated_allowance);
 
-          Self::deposit_event(RawEvent::Approval(from.clone(), to.clone(), value));
-          Self::_transfer(from, to, value)
+          // transfer before touching the allowance so a failed transfer leaves it intact
+          Self::_transfer(asset_id, from.clone(), to, value)?;
+
+          // insert the new allowance value of this owner and spender combination
+          <Allowance<T>>::insert((asset_id, from.clone(), spender.clone()), updated_allowance);
+
+          // raise the approval event with the remaining allowance
+          Self::deposit_event(RawEvent::Approval(asset_id, from, spender, updated_allowance));
+          Ok(())
       }
   }
 }
@@ -73,18 +522,46 @@ decl_storage! {
   trait Store for Module<T: Trait> as Token {
     // bool flag to allow init to be called only once
     Init get(is_init): bool;
-    // total supply of the token
-    // set in the genesis config
-    // see ../src/chain_spec.rs - line 105
-    TotalSupply get(total_supply) config(): T::TokenBalance;
+    // next free asset id
+    NextAssetId get(next_asset_id): AssetId;
+    // total supply of each asset
+    // always equals the free balances plus the locked deposits plus the unreleased part of
+    // vesting schedules of the asset, burns, fees and slashes take tokens out of both sides
+    // the default asset's supply is set in the genesis config
+    // see ../src/chain_spec.rs - line 118
+    TotalSupply get(total_supply) config(): map AssetId => T::TokenBalance;
+    // account allowed to mint and change metadata of an asset
+    AssetOwner get(asset_owner): map AssetId => Option<T::AccountId>;
+    // accounts allowed to mint and burn an asset besides its owner
+    Minters get(is_minter): map (AssetId, T::AccountId) => bool;
+    // maximum total supply of an asset, unlimited if not set
+    SupplyCap get(supply_cap): map AssetId => Option<T::TokenBalance>;
+    // name, symbol and decimals of an asset
+    Metadata get(metadata): map AssetId => AssetMetadata;
     // mapping of balances to accounts
-    BalanceOf get(balance_of): map T::AccountId => T::TokenBalance;
+    BalanceOf get(balance_of): map (AssetId, T::AccountId) => T::TokenBalance;
     // mapping of allowances to accounts
-    Allowance get(allowance): map (T::AccountId, T::AccountId) => T::TokenBalance;
+    Allowance get(allowance): map (AssetId, T::AccountId, T::AccountId) => T::TokenBalance;
+    // number of permits used by each owner, part of every permit signature
+    PermitNonces get(permit_nonce): map T::AccountId => u64;
+    // vesting schedules granted to an account, removed once fully released
+    Vesting get(vesting_schedules): map T::AccountId => Vec<VestingSchedule<T::TokenBalance, T::BlockNumber>>;
+    // history of an account's balance as (block, balance at the end of that block)
+    // indexed from 0 in block order, used to look up balances in the past
+    // one entry per checkpoint, so recording a new balance never rewrites the history
+    Checkpoints get(checkpoint): map (AssetId, T::AccountId, u32) => (T::BlockNumber, T::TokenBalance);
+    // number of checkpoints recorded for an account's balance
+    CheckpointCount get(checkpoint_count): map (AssetId, T::AccountId) => u32;
     // stores the total deposit for a listing
-    // maps a listing hash with the total tokensface
+    // maps a lock id with the total tokens locked under it
     // TCR specific; not part of standard ERC20 interface
-    LockedDeposits get(locked_deposits): map (T::AccountId, u32) => T::TokenBalance;
+    Locks get(locked_deposits): map (AssetId, LockId<T::AccountId>) => T::TokenBalance;
+    // account that locked a deposit
+    LockOwners get(lock_owner): map (AssetId, LockId<T::AccountId>) => Option<T::AccountId>;
+    // ids of the deposits an account currently has locked
+    LocksOf get(lock_ids_of): map T::AccountId => Vec<(AssetId, LockId<T::AccountId>)>;
+    // total amount an account currently has locked in an asset
+    TotalLocked get(total_locked): map (AssetId, T::AccountId) => T::TokenBalance;
   }
 }
 
@@ -92,11 +569,47 @@ decl_storage! {
 decl_event!(
     pub enum Event<T> where AccountId = <T as system::Trait>::AccountId, TokenBalance = <T as self::Trait>::TokenBalance {
         // event for transfer of tokens
-        // from, to, value
-        Transfer(AccountId, AccountId, TokenBalance),
-        // event when an approval is made
-        // owner, spender, value
-        Approval(AccountId, AccountId, TokenBalance),
+        // asset, from, to, value
+        Transfer(AssetId, AccountId, AccountId, TokenBalance),
+        // event when an allowance changes
+        // asset, owner, spender, allowance after the change
+        Approval(AssetId, AccountId, AccountId, TokenBalance),
+        // event when an asset is created
+        // asset, owner, initial supply
+        AssetCreated(AssetId, AccountId, TokenBalance),
+        // event when new tokens are issued
+        // asset, to, value
+        Mint(AssetId, AccountId, TokenBalance),
+        // event when tokens are destroyed
+        // asset, from, value
+        Burn(AssetId, AccountId, TokenBalance),
+        // event when an account may mint and burn an asset
+        // asset, minter
+        MinterAdded(AssetId, AccountId),
+        // event when an account may no longer mint and burn an asset
+        // asset, minter
+        MinterRemoved(AssetId, AccountId),
+        // event when the supply cap of an asset changes
+        // asset, cap
+        SupplyCapChanged(AssetId, Option<TokenBalance>),
+        // event when tokens are locked
+        // asset, owner, lock id, reason, value
+        Locked(AssetId, AccountId, LockId<AccountId>, LockReason, TokenBalance),
+        // event when locked tokens are released
+        // asset, to, lock id, value
+        Unlocked(AssetId, AccountId, LockId<AccountId>, TokenBalance),
+        // event when locked tokens are destroyed
+        // asset, lock id, value
+        Slashed(AssetId, LockId<AccountId>, TokenBalance),
+        // event when a vesting schedule is granted
+        // asset, to, total
+        VestingScheduleAdded(AssetId, AccountId, TokenBalance),
+        // event when matured tokens are released
+        // asset, to, value
+        Vested(AssetId, AccountId, TokenBalance),
+        // event when an asset's metadata changes
+        // asset, metadata
+        MetadataSet(AssetId, AssetMetadata),
     }
 );
 
@@ -108,76 +621,641 @@ decl_event!(
 /// All functions in the impl module section are not part of public interface because they are not part of the Call enum
 impl<T: Trait> Module<T> {
     // initialize the token
-    // transfers the total_supply amout to the caller
+    // transfers the total_supply amout of the default asset to the caller
+    // the caller becomes the owner of the default asset
     // the token becomes usable
     // not part of ERC20 standard interface
     // similar to the ERC20 smart contract constructor
     pub fn init(sender: T::AccountId) -> Result {
         ensure!(Self::is_init() == false, "Token already initialized.");
 
-        <BalanceOf<T>>::insert(sender, Self::total_supply());
+        Self::set_balance((DEFAULT_ASSET, sender.clone()), Self::total_supply(DEFAULT_ASSET));
+        <AssetOwner<T>>::insert(DEFAULT_ASSET, sender);
         <Init<T>>::put(true);
 
         Ok(())
     }
 
     // lock user deposits for curation actions
+    // all deposits under one lock id must be locked by the same account
+    // nothing is written unless the lock succeeds
     // TCR specific; not part of standard ERC20 interface
-    pub fn lock(from: T::AccountId, value: T::TokenBalance, uriClassIndexTuple: (T::AccountId, u32)) -> Result {
-        ensure!(<BalanceOf<T>>::exists(from.clone()), "Account does not own this token");
+    pub fn lock(asset_id: AssetId, from: T::AccountId, value: T::TokenBalance, lock_id: LockId<T::AccountId>) -> rstd::result::Result<(), LockError> {
+        if !<BalanceOf<T>>::exists((asset_id, from.clone())) {
+            return Err(LockError::NoBalance);
+        }
 
-        let sender_balance = Self::balance_of(from.clone());
-        ensure!(sender_balance > value, "Not enough balance.");
-        let updated_from_balance = sender_balance.checked_sub(&value).ok_or("overflow in calculating balance")?;
-        let deposit = Self::locked_deposits(&uriClassIndexTuple);
-        let updated_deposit = deposit.checked_add(&value).ok_or("overflow in calculating deposit")?;
+        let lockKey = (asset_id, lock_id.clone());
+        if let Some(owner) = Self::lock_owner(&lockKey) {
+            if owner != from {
+                return Err(LockError::WrongOwner);
+            }
+        }
+
+        let sender_balance = Self::balance_of((asset_id, from.clone()));
+        let updated_from_balance = sender_balance.checked_sub(&value).ok_or(LockError::InsufficientBalance)?;
+        Self::locked_deposits(&lockKey).checked_add(&value).ok_or(LockError::Overflow)?;
+        Self::total_locked((asset_id, from.clone())).checked_add(&value).ok_or(LockError::Overflow)?;
 
         // deduct the deposit from balance
-        <BalanceOf<T>>::insert(from, updated_from_balance);
-        
+        Self::set_balance((asset_id, from.clone()), updated_from_balance);
+
         // add to deposits
-        <LockedDeposits<T>>::insert(uriClassIndexTuple, updated_deposit);
+        Self::add_to_lock(asset_id, from.clone(), value, lock_id.clone());
 
+        let reason = lock_id.reason();
+        Self::deposit_event(RawEvent::Locked(asset_id, from, lock_id, reason, value));
         Ok(())
     }
 
-    // unlock user's deposit for reward claims and challenge wins
+    // unlock a deposit locked by `owner` to `to` for reward claims and challenge wins
+    // nothing is written unless the unlock succeeds
     // TCR specific; not part of standard ERC20 interface
-    pub fn unlock(to: T::AccountId, value: T::TokenBalance, uriClassIndexTuple: (T::AccountId, u32)) -> Result {
-        let to_balance = Self::balance_of(to.clone());
-        let updated_to_balance = to_balance.checked_add(&value).ok_or("overflow in calculating balance")?;
-        let deposit = Self::locked_deposits(&uriClassIndexTuple);
-        let updated_deposit = deposit.checked_sub(&value).ok_or("overflow in calculating deposit")?;
+    pub fn unlock(asset_id: AssetId, owner: T::AccountId, to: T::AccountId, value: T::TokenBalance, lock_id: LockId<T::AccountId>) -> rstd::result::Result<(), LockError> {
+        Self::ensure_locked(asset_id, owner, value, lock_id.clone())?;
+
+        let to_balance = Self::balance_of((asset_id, to.clone()));
+        let updated_to_balance = to_balance.checked_add(&value).ok_or(LockError::Overflow)?;
+
+        Self::release(asset_id, value, lock_id.clone())?;
 
         // add to user's balance
-        <BalanceOf<T>>::insert(to, updated_to_balance);
+        Self::set_balance((asset_id, to.clone()), updated_to_balance);
+
+        Self::deposit_event(RawEvent::Unlocked(asset_id, to, lock_id, value));
+        Ok(())
+    }
+
+    // destroy part of a locked deposit, e.g. for a lost challenge
+    // nothing is written unless the slash succeeds
+    // TCR specific; not part of standard ERC20 interface
+    pub fn slash(asset_id: AssetId, value: T::TokenBalance, lock_id: LockId<T::AccountId>) -> rstd::result::Result<(), LockError> {
+        let updated_supply = Self::total_supply(asset_id).checked_sub(&value).ok_or(LockError::Overflow)?;
+
+        Self::release(asset_id, value, lock_id.clone())?;
+
+        // slashed tokens leave the supply
+        <TotalSupply<T>>::insert(asset_id, updated_supply);
+
+        Self::deposit_event(RawEvent::Slashed(asset_id, lock_id, value));
+        Ok(())
+    }
+
+    // check that `owner` has at least `value` locked under a lock id
+    // lets callers verify several unlocks before performing any of them
+    pub fn ensure_locked(asset_id: AssetId, owner: T::AccountId, value: T::TokenBalance, lock_id: LockId<T::AccountId>) -> rstd::result::Result<(), LockError> {
+        let lockKey = (asset_id, lock_id);
+        if let Some(lock_owner) = Self::lock_owner(&lockKey) {
+            if lock_owner != owner {
+                return Err(LockError::WrongOwner);
+            }
+        }
+        if Self::locked_deposits(&lockKey) < value {
+            return Err(LockError::InsufficientDeposit);
+        }
+
+        Ok(())
+    }
+
+    // unlock a deposit locked by `owner` into a vesting schedule for `to`
+    // behaves like unlock, except the tokens mature block by block
+    // TCR specific; not part of standard ERC20 interface
+    pub fn unlock_vesting(asset_id: AssetId,
+                          owner: T::AccountId,
+                          to: T::AccountId,
+                          value: T::TokenBalance,
+                          lock_id: LockId<T::AccountId>,
+                          per_block: T::TokenBalance,
+                          cliff: T::BlockNumber) -> rstd::result::Result<(), LockError> {
+        Self::ensure_locked(asset_id, owner, value, lock_id.clone())?;
+        Self::ensure_can_vest(&to, 1)?;
+        Self::release(asset_id, value, lock_id.clone())?;
+
+        Self::deposit_event(RawEvent::Unlocked(asset_id, to.clone(), lock_id, value));
+        Self::add_vesting_schedule(asset_id, to, value, per_block, cliff);
+        Ok(())
+    }
+
+    // check that `count` more vesting schedules can be granted to `who`
+    // lets callers verify several unlocks into vesting before performing any of them
+    pub fn ensure_can_vest(who: &T::AccountId, count: usize) -> rstd::result::Result<(), LockError> {
+        if Self::vesting_schedules(who).len().saturating_add(count) > MAX_VESTING_SCHEDULES {
+            return Err(LockError::TooManySchedules);
+        }
+        Ok(())
+    }
+
+    // message an owner signs for a permit
+    pub fn permit_message(asset_id: AssetId,
+                          owner: &T::AccountId,
+                          spender: &T::AccountId,
+                          value: T::TokenBalance,
+                          nonce: u64,
+                          deadline: T::BlockNumber) -> Vec<u8> {
+        let genesis_hash = <system::Module<T>>::block_hash(T::BlockNumber::zero());
+        (PERMIT_TAG, genesis_hash, asset_id, owner, spender, value, nonce, deadline).encode()
+    }
+
+    // take a fee from an account, the tokens leave the supply
+    // not part of ERC20 standard interface
+    pub fn charge_fee(asset_id: AssetId, who: T::AccountId, value: T::TokenBalance) -> Result {
+        let updated_balance = Self::balance_of((asset_id, who.clone())).checked_sub(&value).ok_or("Not enough balance to pay fee.")?;
+        let updated_supply = Self::total_supply(asset_id).checked_sub(&value).ok_or("overflow in calculating supply")?;
+
+        <TotalSupply<T>>::insert(asset_id, updated_supply);
+        Self::set_balance((asset_id, who.clone()), updated_balance);
+
+        Self::deposit_event(RawEvent::Burn(asset_id, who, value));
+        Ok(())
+    }
+
+    // balance of an account at the end of block `at`
+    // voting weighted by tokens should use this with a block fixed before voting opens,
+    // so tokens transferred after that block cannot be counted twice
+    pub fn balance_of_at(asset_id: AssetId, who: T::AccountId, at: T::BlockNumber) -> T::TokenBalance {
+        let count = Self::checkpoint_count((asset_id, who.clone()));
+        if count == 0 {
+            // the balance has not changed since checkpoints were introduced
+            return Self::balance_of((asset_id, who));
+        }
+
+        // binary search for the number of checkpoints recorded up to block `at`
+        let (mut low, mut high) = (0, count);
+        while low < high {
+            let middle = low + (high - low) / 2;
+            if Self::checkpoint((asset_id, who.clone(), middle)).0 <= at {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
+        }
+
+        match low {
+            0 => Zero::zero(),
+            index => Self::checkpoint((asset_id, who, index - 1)).1,
+        }
+    }
+
+    // all deposits an account has locked: asset, lock id, reason and amount
+    pub fn locks_of(who: T::AccountId) -> Vec<(AssetId, LockId<T::AccountId>, LockReason, T::TokenBalance)> {
+        Self::lock_ids_of(&who)
+            .into_iter()
+            .map(|lockKey| {
+                let deposit = Self::locked_deposits(&lockKey);
+                let reason = lockKey.1.reason();
+                (lockKey.0, lockKey.1, reason, deposit)
+            })
+            .collect()
+    }
+
+    // take value out of a locked deposit and its owner's index
+    fn release(asset_id: AssetId, value: T::TokenBalance, lock_id: LockId<T::AccountId>) -> rstd::result::Result<(), LockError> {
+        let lockKey = (asset_id, lock_id);
+        let deposit = Self::locked_deposits(&lockKey);
+        let updated_deposit = deposit.checked_sub(&value).ok_or(LockError::InsufficientDeposit)?;
+
+        let owner = Self::lock_owner(&lockKey);
+        let updated_total_locked = match owner {
+            Some(ref owner) => Some(Self::total_locked((asset_id, owner.clone())).checked_sub(&value).ok_or(LockError::Overflow)?),
+            None => None,
+        };
 
         // decrease from locked deposits
-        <LockedDeposits<T>>::insert(uriClassIndexTuple, updated_deposit);
+        if updated_deposit.is_zero() {
+            <Locks<T>>::remove(&lockKey);
+        } else {
+            <Locks<T>>::insert(&lockKey, updated_deposit);
+        }
+
+        // update the owner's index
+        if let (Some(owner), Some(updated_total_locked)) = (owner, updated_total_locked) {
+            <TotalLocked<T>>::insert((asset_id, owner.clone()), updated_total_locked);
+            if updated_deposit.is_zero() {
+                <LockOwners<T>>::remove(&lockKey);
+                <LocksOf<T>>::mutate(&owner, |ids| ids.retain(|id| *id != lockKey));
+            }
+        }
 
         Ok(())
     }
 
+    // add value to a deposit and index it under its owner
+    // the caller has checked the owner and that nothing overflows
+    fn add_to_lock(asset_id: AssetId, owner: T::AccountId, value: T::TokenBalance, lock_id: LockId<T::AccountId>) {
+        let lockKey = (asset_id, lock_id);
+        <Locks<T>>::mutate(&lockKey, |deposit| *deposit = *deposit + value);
+
+        if !<LockOwners<T>>::exists(&lockKey) {
+            <LockOwners<T>>::insert(&lockKey, owner.clone());
+            <LocksOf<T>>::mutate(&owner, |ids| ids.push(lockKey.clone()));
+        }
+        <TotalLocked<T>>::mutate((asset_id, owner), |total_locked| *total_locked = *total_locked + value);
+    }
+
+    // ensure the origin is root or the owner of the asset
+    fn ensure_asset_admin(origin: T::Origin, asset_id: AssetId) -> Result {
+        let origin: Option<system::RawOrigin<T::AccountId>> = origin.into();
+        match origin {
+            Some(system::RawOrigin::Root) => Ok(()),
+            Some(system::RawOrigin::Signed(sender)) => {
+                ensure!(Self::asset_owner(asset_id) == Some(sender), "Only root or the asset owner can do this.");
+                Ok(())
+            },
+            _ => Err("bad origin: expected to be root or signed"),
+        }
+    }
+
+    // ensure the origin is root, the owner of the asset or one of its minters
+    fn ensure_minter(origin: T::Origin, asset_id: AssetId) -> Result {
+        let origin: Option<system::RawOrigin<T::AccountId>> = origin.into();
+        match origin {
+            Some(system::RawOrigin::Root) => Ok(()),
+            Some(system::RawOrigin::Signed(sender)) => {
+                ensure!(Self::can_mint(asset_id, &sender), "Only root, the asset owner or a minter can do this.");
+                Ok(())
+            },
+            _ => Err("bad origin: expected to be root or signed"),
+        }
+    }
+
+    // whether an account is the owner of the asset or one of its minters
+    fn can_mint(asset_id: AssetId, who: &T::AccountId) -> bool {
+        Self::asset_owner(asset_id).as_ref() == Some(who) || Self::is_minter((asset_id, who.clone()))
+    }
+
+    // grant `to` a vesting schedule starting at the current block
+    // the tokens must already have been taken out of circulation by the caller,
+    // which has also checked that `to` holds fewer than MAX_VESTING_SCHEDULES
+    fn add_vesting_schedule(asset_id: AssetId, to: T::AccountId, value: T::TokenBalance, per_block: T::TokenBalance, cliff: T::BlockNumber) {
+        let schedule = VestingSchedule {
+            asset_id,
+            total: value,
+            released: Zero::zero(),
+            per_block,
+            start: <system::Module<T>>::block_number(),
+            cliff,
+        };
+        <Vesting<T>>::mutate(&to, |schedules| schedules.push(schedule));
+
+        Self::deposit_event(RawEvent::VestingScheduleAdded(asset_id, to, value));
+    }
+
+    // release matured tokens of every schedule of an account
+    fn _vest(who: T::AccountId) -> Result {
+        let now = <system::Module<T>>::block_number();
+        let mut schedules = Self::vesting_schedules(&who);
+        ensure!(!schedules.is_empty(), "No vesting schedules.");
+
+        // work out the total release of each asset before changing any balance
+        let mut releases: Vec<(AssetId, T::TokenBalance)> = Vec::new();
+        for schedule in schedules.iter_mut() {
+            let releasable = schedule.vested_at(now).checked_sub(&schedule.released).unwrap_or_else(Zero::zero);
+            if releasable.is_zero() {
+                continue;
+            }
+            schedule.released = schedule.released.checked_add(&releasable).ok_or("overflow in calculating vesting")?;
+            let asset_id = schedule.asset_id;
+            match releases.iter_mut().find(|(id, _)| *id == asset_id) {
+                Some(release) => release.1 = release.1.checked_add(&releasable).ok_or("overflow in calculating vesting")?,
+                None => releases.push((asset_id, releasable)),
+            }
+        }
+
+        let mut credits: Vec<(AssetId, T::TokenBalance, T::TokenBalance)> = Vec::new();
+        for (asset_id, value) in releases {
+            let balance = Self::balance_of((asset_id, who.clone()));
+            let updated_balance = balance.checked_add(&value).ok_or("overflow in calculating balance")?;
+            credits.push((asset_id, value, updated_balance));
+        }
+
+        // credit the released tokens
+        for (asset_id, value, updated_balance) in credits {
+            Self::set_balance((asset_id, who.clone()), updated_balance);
+            Self::deposit_event(RawEvent::Vested(asset_id, who.clone(), value));
+        }
+
+        // drop schedules that are fully released
+        schedules.retain(|schedule| schedule.released < schedule.total);
+        if schedules.is_empty() {
+            <Vesting<T>>::remove(&who);
+        } else {
+            <Vesting<T>>::insert(&who, schedules);
+        }
+
+        Ok(())
+    }
+
+    // write an account's balance and record it in the account's checkpoints
+    // every change to BalanceOf must go through here
+    fn set_balance(key: (AssetId, T::AccountId), balance: T::TokenBalance) {
+        let now = <system::Module<T>>::block_number();
+        let previous = Self::balance_of(&key);
+
+        let (asset_id, who) = key.clone();
+        let mut count = Self::checkpoint_count(&key);
+
+        // balances set before checkpoints existed are treated as held since genesis
+        if count == 0 && !previous.is_zero() {
+            <Checkpoints<T>>::insert((asset_id, who.clone(), 0), (T::BlockNumber::zero(), previous));
+            count = 1;
+        }
+
+        // a balance changed twice in one block keeps a single checkpoint
+        let last_block = match count {
+            0 => None,
+            _ => Some(Self::checkpoint((asset_id, who.clone(), count - 1)).0),
+        };
+        if last_block == Some(now) {
+            <Checkpoints<T>>::insert((asset_id, who, count - 1), (now, balance));
+        } else {
+            <Checkpoints<T>>::insert((asset_id, who, count), (now, balance));
+            count += 1;
+        }
+
+        <CheckpointCount<T>>::insert(&key, count);
+        <BalanceOf<T>>::insert(key, balance);
+    }
+
+    // overwrite the allowance of an owner and spender combination
+    fn _set_allowance(asset_id: AssetId, owner: T::AccountId, spender: T::AccountId, value: T::TokenBalance) {
+        <Allowance<T>>::insert((asset_id, owner.clone(), spender.clone()), value);
+
+        // raise the approval event with the new allowance
+        Self::deposit_event(RawEvent::Approval(asset_id, owner, spender, value));
+    }
+
     // internal transfer function for ERC20 interface
     fn _transfer(
+        asset_id: AssetId,
         from: T::AccountId,
         to: T::AccountId,
         value: T::TokenBalance,
     ) -> Result {
-        ensure!(<BalanceOf<T>>::exists(from.clone()), "Account does not own this token");
-        let sender_balance = Self::balance_of(from.clone());
+        ensure!(<BalanceOf<T>>::exists((asset_id, from.clone())), "Account does not own this token");
+        let sender_balance = Self::balance_of((asset_id, from.clone()));
         ensure!(sender_balance >= value, "Not enough balance.");
         let updated_from_balance = sender_balance.checked_sub(&value).ok_or("overflow in calculating balance")?;
-        let receiver_balance = Self::balance_of(to.clone());
+        let receiver_balance = Self::balance_of((asset_id, to.clone()));
         let updated_to_balance = receiver_balance.checked_add(&value).ok_or("overflow in calculating balance")?;
-        
+
         // reduce sender's balance
-        <BalanceOf<T>>::insert(from.clone(), updated_from_balance);
+        Self::set_balance((asset_id, from.clone()), updated_from_balance);
 
         // increase receiver's balance
-        <BalanceOf<T>>::insert(to.clone(), updated_to_balance);
+        Self::set_balance((asset_id, to.clone()), updated_to_balance);
 
-        Self::deposit_event(RawEvent::Transfer(from, to, value));
+        Self::deposit_event(RawEvent::Transfer(asset_id, from, to, value));
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use runtime_io::with_externalities;
+    use primitives::{H256, Blake2Hasher};
+    use support::{impl_outer_origin, assert_ok, assert_noop};
+    use runtime_primitives::{
+        BuildStorage,
+        traits::{BlakeTwo256, IdentityLookup, Lazy},
+        testing::{Digest, DigestItem, Header},
+    };
+
+    impl_outer_origin! {
+        pub enum Origin for Test {}
+    }
+
+    #[derive(Clone, Eq, PartialEq, Debug)]
+    pub struct Test;
+
+    impl system::Trait for Test {
+        type Origin = Origin;
+        type Index = u64;
+        type BlockNumber = u64;
+        type Hash = H256;
+        type Hashing = BlakeTwo256;
+        type Digest = Digest;
+        type AccountId = u64;
+        type Lookup = IdentityLookup<u64>;
+        type Header = Header;
+        type Event = ();
+        type Log = DigestItem;
+    }
+
+    // a signature is valid for the account it names
+    #[derive(Encode, Decode, Clone, PartialEq, Eq, Debug)]
+    pub struct TestSignature(u64);
+
+    impl Verify for TestSignature {
+        type Signer = u64;
+        fn verify<L: Lazy<[u8]>>(&self, _msg: L, signer: &u64) -> bool {
+            self.0 == *signer
+        }
+    }
+
+    impl Trait for Test {
+        type Event = ();
+        type TokenBalance = u64;
+        type Signature = TestSignature;
+    }
+
+    type Token = Module<Test>;
+
+    const OWNER: u64 = 1;
+    const SPENDER: u64 = 2;
+    const RECIPIENT: u64 = 3;
+
+    // OWNER holds the whole supply of the default asset
+    fn new_test_ext() -> runtime_io::TestExternalities<Blake2Hasher> {
+        let mut t = system::GenesisConfig::<Test>::default().build_storage().unwrap().0;
+        t.extend(GenesisConfig::<Test> {
+            total_supply: vec![(DEFAULT_ASSET, 1000)],
+        }.build_storage().unwrap().0);
+        let mut ext: runtime_io::TestExternalities<Blake2Hasher> = t.into();
+        with_externalities(&mut ext, || {
+            assert_ok!(Token::init(OWNER));
+        });
+        ext
+    }
+
+    #[test]
+    fn transfer_from_credits_recipient_not_spender() {
+        with_externalities(&mut new_test_ext(), || {
+            assert_ok!(Token::approve(Origin::signed(OWNER), DEFAULT_ASSET, SPENDER, 100));
+            assert_ok!(Token::transfer_from(Origin::signed(SPENDER), DEFAULT_ASSET, OWNER, RECIPIENT, 60));
+
+            assert_eq!(Token::balance_of((DEFAULT_ASSET, OWNER)), 940);
+            assert_eq!(Token::balance_of((DEFAULT_ASSET, RECIPIENT)), 60);
+            assert_eq!(Token::balance_of((DEFAULT_ASSET, SPENDER)), 0);
+            assert_eq!(Token::allowance((DEFAULT_ASSET, OWNER, SPENDER)), 40);
+        });
+    }
+
+    #[test]
+    fn transfer_from_rejects_insufficient_allowance() {
+        with_externalities(&mut new_test_ext(), || {
+            assert_ok!(Token::approve(Origin::signed(OWNER), DEFAULT_ASSET, SPENDER, 50));
+            assert_noop!(
+                Token::transfer_from(Origin::signed(SPENDER), DEFAULT_ASSET, OWNER, RECIPIENT, 60),
+                "Not enough allowance."
+            );
+            assert_eq!(Token::allowance((DEFAULT_ASSET, OWNER, SPENDER)), 50);
+        });
+    }
+
+    #[test]
+    fn failed_transfer_keeps_allowance() {
+        with_externalities(&mut new_test_ext(), || {
+            // allowance above the owner's balance, so the transfer itself fails
+            assert_ok!(Token::approve(Origin::signed(OWNER), DEFAULT_ASSET, SPENDER, 5000));
+            assert_noop!(
+                Token::transfer_from(Origin::signed(SPENDER), DEFAULT_ASSET, OWNER, RECIPIENT, 2000),
+                "Not enough balance."
+            );
+            assert_eq!(Token::allowance((DEFAULT_ASSET, OWNER, SPENDER)), 5000);
+            assert_eq!(Token::balance_of((DEFAULT_ASSET, OWNER)), 1000);
+        });
+    }
+
+    #[test]
+    fn transfer_from_rejects_non_approved_signer() {
+        with_externalities(&mut new_test_ext(), || {
+            assert_ok!(Token::approve(Origin::signed(OWNER), DEFAULT_ASSET, SPENDER, 100));
+
+            // the recipient was never approved, it cannot spend the spender's allowance
+            assert_noop!(
+                Token::transfer_from(Origin::signed(RECIPIENT), DEFAULT_ASSET, OWNER, RECIPIENT, 10),
+                "Allowance does not exist."
+            );
+            assert_eq!(Token::allowance((DEFAULT_ASSET, OWNER, SPENDER)), 100);
+        });
+    }
+
+    const OTHER: u64 = 4;
+    const ACCOUNTS: [u64; 4] = [OWNER, SPENDER, RECIPIENT, OTHER];
+
+    // small deterministic generator, a failing sequence can be replayed from its seed
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self, bound: u64) -> u64 {
+            self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (self.0 >> 33) % bound
+        }
+
+        fn account(&mut self) -> u64 {
+            ACCOUNTS[self.next(ACCOUNTS.len() as u64) as usize]
+        }
+
+        fn lock_id(&mut self) -> LockId<u64> {
+            let (who, index) = (self.account(), self.next(2) as u32);
+            match self.next(3) {
+                0 => LockId::Funding(who, index),
+                1 => LockId::Stake(who, index),
+                _ => LockId::Challenge(who, index),
+            }
+        }
+    }
+
+    // tokens of the default asset held in any form: free, locked or still vesting
+    fn accounted_for() -> u64 {
+        ACCOUNTS.iter().map(|who| {
+            let free = Token::balance_of((DEFAULT_ASSET, *who));
+            let locked: u64 = Token::locks_of(*who)
+                .into_iter()
+                .filter(|(asset_id, _, _, _)| *asset_id == DEFAULT_ASSET)
+                .map(|(_, _, _, deposit)| deposit)
+                .sum();
+            let vesting: u64 = Token::vesting_schedules(who)
+                .into_iter()
+                .filter(|schedule| schedule.asset_id == DEFAULT_ASSET)
+                .map(|schedule| schedule.total - schedule.released)
+                .sum();
+            free + locked + vesting
+        }).sum()
+    }
+
+    // one random operation that moves tokens, failures are expected and must not change anything
+    fn random_operation(rng: &mut Rng) {
+        let value = rng.next(300);
+        let _ = match rng.next(10) {
+            0 => Token::transfer(Origin::signed(rng.account()), DEFAULT_ASSET, rng.account(), value),
+            1 => Token::lock(DEFAULT_ASSET, rng.account(), value, rng.lock_id()).map_err(Into::into),
+            2 => Token::unlock(DEFAULT_ASSET, rng.account(), rng.account(), value, rng.lock_id()).map_err(Into::into),
+            3 => Token::slash(DEFAULT_ASSET, value, rng.lock_id()).map_err(Into::into),
+            4 => Token::unlock_vesting(DEFAULT_ASSET, rng.account(), rng.account(), value, rng.lock_id(), rng.next(20) + 1, rng.next(50)).map_err(Into::into),
+            5 => Token::vested_transfer(Origin::signed(rng.account()), DEFAULT_ASSET, rng.account(), value, rng.next(20) + 1, rng.next(50)),
+            6 => Token::vest(Origin::signed(rng.account())),
+            7 => Token::charge_fee(DEFAULT_ASSET, rng.account(), value),
+            8 => Token::approve(Origin::signed(rng.account()), DEFAULT_ASSET, OWNER, value),
+            _ => Token::burn(Origin::signed(OWNER), DEFAULT_ASSET, rng.account(), value),
+        };
+    }
+
+    #[test]
+    fn supply_is_always_accounted_for() {
+        for seed in 0..20 {
+            with_externalities(&mut new_test_ext(), || {
+                let mut rng = Rng(seed);
+                // spread the supply so every account can take part
+                for who in &ACCOUNTS[1..] {
+                    assert_ok!(Token::transfer(Origin::signed(OWNER), DEFAULT_ASSET, *who, 200));
+                }
+
+                for block in 1..200 {
+                    system::Module::<Test>::set_block_number(block);
+                    random_operation(&mut rng);
+                    assert_eq!(Token::total_supply(DEFAULT_ASSET), accounted_for(), "seed {} block {}", seed, block);
+                }
+            });
+        }
+    }
+
+    #[test]
+    fn slash_and_fees_leave_the_supply() {
+        with_externalities(&mut new_test_ext(), || {
+            assert_ok!(Token::lock(DEFAULT_ASSET, OWNER, 100, LockId::Challenge(OWNER, 0)));
+            assert_ok!(Token::slash(DEFAULT_ASSET, 40, LockId::Challenge(OWNER, 0)));
+            assert_ok!(Token::charge_fee(DEFAULT_ASSET, OWNER, 10));
+
+            assert_eq!(Token::total_supply(DEFAULT_ASSET), 950);
+            assert_eq!(Token::locked_deposits((DEFAULT_ASSET, LockId::Challenge(OWNER, 0))), 60);
+            assert_eq!(accounted_for(), 950);
+        });
+    }
+
+    #[test]
+    fn vesting_counts_towards_the_supply() {
+        with_externalities(&mut new_test_ext(), || {
+            assert_ok!(Token::vested_transfer(Origin::signed(OWNER), DEFAULT_ASSET, RECIPIENT, 100, 10, 0));
+            assert_eq!(accounted_for(), 1000);
+
+            system::Module::<Test>::set_block_number(3);
+            assert_ok!(Token::vest(Origin::signed(RECIPIENT)));
+
+            assert_eq!(Token::balance_of((DEFAULT_ASSET, RECIPIENT)), 30);
+            assert_eq!(accounted_for(), 1000);
+        });
+    }
+
+    #[test]
+    fn balance_of_at_reads_checkpoints() {
+        with_externalities(&mut new_test_ext(), || {
+            for block in 1..6 {
+                system::Module::<Test>::set_block_number(block * 10);
+                assert_ok!(Token::transfer(Origin::signed(OWNER), DEFAULT_ASSET, RECIPIENT, 10));
+                // a second change in the same block keeps one checkpoint
+                assert_ok!(Token::transfer(Origin::signed(OWNER), DEFAULT_ASSET, RECIPIENT, 10));
+            }
+
+            assert_eq!(Token::checkpoint_count((DEFAULT_ASSET, RECIPIENT)), 5);
+            assert_eq!(Token::balance_of_at(DEFAULT_ASSET, RECIPIENT, 9), 0);
+            assert_eq!(Token::balance_of_at(DEFAULT_ASSET, RECIPIENT, 10), 20);
+            assert_eq!(Token::balance_of_at(DEFAULT_ASSET, RECIPIENT, 35), 60);
+            assert_eq!(Token::balance_of_at(DEFAULT_ASSET, RECIPIENT, 100), 100);
+            // the owner's genesis balance is recorded as held since block 0
+            assert_eq!(Token::balance_of_at(DEFAULT_ASSET, OWNER, 5), 1000);
+        });
+    }
+}
@@ -1,7 +1,7 @@
 use primitives::{ed25519, sr25519, Pair};
 use bnft_runtime::{
 	AccountId, GenesisConfig, ConsensusConfig, TimestampConfig, BalancesConfig,
-	SudoConfig, IndicesConfig, TokenConfig, BnftConfig,
+	SudoConfig, IndicesConfig, TokenConfig, BnftConfig, PreimageConfig,
 };
 use substrate_service;
 
@@ -116,11 +116,16 @@ fn testnet_genesis(initial_authorities: Vec<AuthorityId>, endowed_accounts: Vec<
 		key: root_key,
 	    }),
             token: Some(TokenConfig {
-                // setting total supply of tokens to 21B
-                total_supply: 21000000000,
+                // setting total supply of the default token to 21B
+                total_supply: vec![(0, 21000000000)],
             }),
             bnft: Some(BnftConfig {
                 owner: account_key("Alice"),
             }),
+            preimage: Some(PreimageConfig {
+                // documents up to 64KB
+                max_preimage_size: 65536,
+                preimage_byte_deposit: 1,
+            }),
 	}
 }